@@ -0,0 +1,449 @@
+use crate::dice::RollSpec;
+use crate::{AbilityScores, CharacterGenerator, Class};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Ability {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Wisdom,
+    Charisma,
+}
+
+impl Ability {
+    // In the order scores are rolled and listed
+    pub const ALL: [Ability; 6] = [
+        Ability::Strength,
+        Ability::Dexterity,
+        Ability::Constitution,
+        Ability::Intelligence,
+        Ability::Wisdom,
+        Ability::Charisma,
+    ];
+}
+
+// The first ability a priority list names twice, if any
+fn repeated_ability(priority: &[Ability]) -> Option<Ability> {
+    (0..priority.len()).find(|&index| priority[..index].contains(&priority[index])).map(|index| priority[index])
+}
+
+// A priority list that names an ability twice can't be followed
+pub(crate) fn validate_ability_priority(class: &Class) -> anyhow::Result<()> {
+    match repeated_ability(&class.ability_priority) {
+        Some(ability) => Err(anyhow::anyhow!("Class {} lists {} more than once in ability_priority", class.name, ability)),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for Ability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Ability::Strength => "strength",
+            Ability::Dexterity => "dexterity",
+            Ability::Constitution => "constitution",
+            Ability::Intelligence => "intelligence",
+            Ability::Wisdom => "wisdom",
+            Ability::Charisma => "charisma",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<[u8; 6]> for AbilityScores {
+    fn from([strength, dexterity, constitution, intelligence, wisdom, charisma]: [u8; 6]) -> Self {
+        Self {
+            strength,
+            dexterity,
+            constitution,
+            intelligence,
+            wisdom,
+            charisma,
+        }
+    }
+}
+
+// How a character's six scores are come by. Every method but `in-order`
+// hands out the best scores by the class's ability_priority, with whatever
+// is left over going to the other abilities at random.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AbilityMethod {
+    // Roll each score in turn, Strength first
+    #[default]
+    InOrder,
+    // Roll six scores, then arrange them
+    Arrange,
+    // Spend the point-buy budget at random, then arrange the scores
+    PointBuy,
+    // Arrange the standard array
+    StandardArray,
+}
+
+impl AbilityMethod {
+    pub const ALL: [AbilityMethod; 4] = [
+        AbilityMethod::InOrder,
+        AbilityMethod::Arrange,
+        AbilityMethod::PointBuy,
+        AbilityMethod::StandardArray,
+    ];
+
+    // Whether the method rolls dice at all
+    pub fn rolls(&self) -> bool {
+        matches!(self, AbilityMethod::InOrder | AbilityMethod::Arrange)
+    }
+
+    // e.g. "3d6", "4d6kh3 (arrange)" or "standard-array"
+    pub fn describe(&self, dice: &RollSpec) -> String {
+        match self {
+            AbilityMethod::InOrder => dice.to_string(),
+            method if method.rolls() => format!("{} ({})", dice, method),
+            method => method.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AbilityMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AbilityMethod::InOrder => "in-order",
+            AbilityMethod::Arrange => "arrange",
+            AbilityMethod::PointBuy => "point-buy",
+            AbilityMethod::StandardArray => "standard-array",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAbilityMethod(pub String);
+
+impl fmt::Display for UnknownAbilityMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = AbilityMethod::ALL.iter().map(ToString::to_string).collect();
+        write!(f, "unknown ability method '{}' (expected one of {})", self.0, names.join(", "))
+    }
+}
+
+impl std::error::Error for UnknownAbilityMethod {}
+
+impl FromStr for AbilityMethod {
+    type Err = UnknownAbilityMethod;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase().replace('_', "-");
+        AbilityMethod::ALL
+            .into_iter()
+            .find(|method| method.to_string() == name)
+            .ok_or_else(|| UnknownAbilityMethod(s.to_string()))
+    }
+}
+
+// Every score starts at `base` and costs one point per step up, to at most
+// `max`. The defaults average out the same as 3d6.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PointBuy {
+    pub base: u8,
+    pub max: u8,
+    pub points: u8,
+}
+
+impl Default for PointBuy {
+    fn default() -> Self {
+        Self {
+            base: 8,
+            max: 16,
+            points: 15,
+        }
+    }
+}
+
+impl PointBuy {
+    // Spend every point, one at a time, on a random score with room left
+    fn spend(&self, rng: &mut impl Rng) -> [u8; 6] {
+        let mut scores = [self.base; 6];
+        for _ in 0..self.points {
+            let open: Vec<usize> = (0..scores.len()).filter(|&index| scores[index] < self.max).collect();
+            let Some(&index) = open.choose(rng) else {
+                break;
+            };
+            scores[index] += 1;
+        }
+        scores
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AbilityRules {
+    pub method: AbilityMethod,
+    pub standard_array: Vec<u8>,
+    pub point_buy: PointBuy,
+}
+
+impl Default for AbilityRules {
+    fn default() -> Self {
+        Self {
+            method: AbilityMethod::default(),
+            standard_array: vec![15, 13, 12, 10, 8, 5],
+            point_buy: PointBuy::default(),
+        }
+    }
+}
+
+impl AbilityRules {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.standard_array.len() != Ability::ALL.len() {
+            return Err(anyhow::anyhow!(
+                "standard_array needs {} scores, not {}",
+                Ability::ALL.len(),
+                self.standard_array.len()
+            ));
+        }
+        let PointBuy { base, max, points } = self.point_buy;
+        if base > max {
+            return Err(anyhow::anyhow!("point_buy base {} is above the max {}", base, max));
+        }
+        let room = usize::from(max - base) * Ability::ALL.len();
+        if usize::from(points) > room {
+            return Err(anyhow::anyhow!(
+                "point_buy has {} points but only {} can be spent between {} and {}",
+                points,
+                room,
+                base,
+                max
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl CharacterGenerator {
+    // Use this ability method instead of the config's. Configs given to
+    // `from_config` haven't been checked yet, so the ability rules and class
+    // priorities the method relies on are checked here.
+    pub fn with_ability_method(mut self, method: AbilityMethod) -> anyhow::Result<Self> {
+        self.config.abilities.validate().map_err(|e| anyhow::anyhow!("Invalid ability rules: {}", e))?;
+        for class in &self.config.classes {
+            validate_ability_priority(class)?;
+        }
+
+        self.config.abilities.method = method;
+        Ok(self)
+    }
+
+    // Six scores by the configured method, the best going to `priority`
+    // first when the method arranges them
+    pub(crate) fn generate_ability_scores(&self, rng: &mut impl Rng, dice: &RollSpec, priority: &[Ability]) -> anyhow::Result<AbilityScores> {
+        let rules = &self.config.abilities;
+        let mut scores: [u8; 6] = match rules.method {
+            AbilityMethod::InOrder => return Ok(AbilityScores::from([(); 6].map(|_| Self::roll_ability_score(rng, dice)))),
+            AbilityMethod::Arrange => [(); 6].map(|_| Self::roll_ability_score(rng, dice)),
+            AbilityMethod::PointBuy => rules.point_buy.spend(rng),
+            AbilityMethod::StandardArray => rules.standard_array.as_slice().try_into().map_err(|_| {
+                anyhow::anyhow!(
+                    "Invalid ability rules: standard_array needs {} scores, not {}",
+                    Ability::ALL.len(),
+                    rules.standard_array.len()
+                )
+            })?,
+        };
+        if let Some(ability) = repeated_ability(priority) {
+            return Err(anyhow::anyhow!("ability_priority lists {} more than once", ability));
+        }
+        scores.sort_unstable_by(|a, b| b.cmp(a));
+
+        // The best scores go down the priority list; the rest are shuffled
+        // over the abilities it leaves out
+        let (best, rest) = scores.split_at_mut(priority.len().min(Ability::ALL.len()));
+        rest.shuffle(rng);
+        // With no ability named twice, the scores left over match the
+        // abilities left over one for one
+        let mut rest = rest.iter();
+        Ok(AbilityScores::from(Ability::ALL.map(|ability| {
+            match priority.iter().position(|&wanted| wanted == ability) {
+                Some(index) => best[index],
+                None => *rest.next().expect("one score is left for every unlisted ability"),
+            }
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{seeded_rng, Config, Constraints};
+
+    fn generator(method: AbilityMethod) -> CharacterGenerator {
+        let config: Config = toml::from_str(
+            r#"
+            species = ["Human"]
+            classes = [
+                { name = "Fighter", ability_priority = ["strength", "constitution"] },
+                "Thief",
+            ]
+
+            [abilities]
+            standard_array = [16, 14, 12, 10, 8, 6]
+            "#,
+        )
+        .unwrap();
+        CharacterGenerator::from_config(config).with_ability_method(method).unwrap()
+    }
+
+    fn scores(character: &crate::Character) -> Vec<u8> {
+        let scores = &character.ability_scores;
+        let mut scores = vec![
+            scores.strength,
+            scores.dexterity,
+            scores.constitution,
+            scores.intelligence,
+            scores.wisdom,
+            scores.charisma,
+        ];
+        scores.sort_unstable();
+        scores
+    }
+
+    fn generate_result(generator: &CharacterGenerator, class: &str, seed: u64) -> anyhow::Result<crate::Character> {
+        let constraints = Constraints {
+            classes: vec![class.to_string()],
+            ..Constraints::default()
+        };
+        generator.generate_character(&mut seeded_rng(seed), 1, &RollSpec::default(), &constraints)
+    }
+
+    fn generate(generator: &CharacterGenerator, class: &str, seed: u64) -> crate::Character {
+        generate_result(generator, class, seed).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for method in AbilityMethod::ALL {
+            assert_eq!(method.to_string().parse::<AbilityMethod>(), Ok(method));
+        }
+        assert_eq!("Point_Buy".parse::<AbilityMethod>(), Ok(AbilityMethod::PointBuy));
+        assert_eq!(AbilityMethod::InOrder.describe(&RollSpec::default()), "3d6");
+        assert_eq!(AbilityMethod::Arrange.describe(&RollSpec::default()), "3d6 (arrange)");
+        assert_eq!(AbilityMethod::StandardArray.describe(&RollSpec::default()), "standard-array");
+        assert_eq!(
+            "best".parse::<AbilityMethod>().unwrap_err().to_string(),
+            "unknown ability method 'best' (expected one of in-order, arrange, point-buy, standard-array)"
+        );
+    }
+
+    #[test]
+    fn test_in_order_is_unchanged() {
+        // Rolled straight down the line, so seeds give the same scores as
+        // before ability methods existed
+        let generator = generator(AbilityMethod::InOrder);
+        let mut rng = seeded_rng(3);
+        let expected = AbilityScores::from([(); 6].map(|_| CharacterGenerator::roll_ability_score(&mut rng, &RollSpec::default())));
+        let scores = generator
+            .generate_ability_scores(&mut seeded_rng(3), &RollSpec::default(), &[Ability::Charisma])
+            .unwrap();
+        assert_eq!(scores, expected);
+    }
+
+    #[test]
+    fn test_standard_array_follows_priority() {
+        let generator = generator(AbilityMethod::StandardArray);
+        for seed in 0..10 {
+            let fighter = generate(&generator, "Fighter", seed);
+            assert_eq!(scores(&fighter), [6, 8, 10, 12, 14, 16]);
+            assert_eq!((fighter.ability_scores.strength, fighter.ability_scores.constitution), (16, 14));
+
+            // No priority, so the array lands anywhere
+            let thief = generate(&generator, "Thief", seed);
+            assert_eq!(scores(&thief), [6, 8, 10, 12, 14, 16]);
+        }
+    }
+
+    #[test]
+    fn test_arrange_puts_best_rolls_first() {
+        let generator = generator(AbilityMethod::Arrange);
+        for seed in 0..20 {
+            let fighter = generate(&generator, "Fighter", seed);
+            let sorted = scores(&fighter);
+            assert_eq!(fighter.ability_scores.strength, sorted[5]);
+            assert_eq!(fighter.ability_scores.constitution, sorted[4]);
+        }
+    }
+
+    #[test]
+    fn test_point_buy_spends_the_budget() {
+        let generator = generator(AbilityMethod::PointBuy);
+        let rules = PointBuy::default();
+        for seed in 0..20 {
+            let fighter = generate(&generator, "Fighter", seed);
+            let sorted = scores(&fighter);
+            assert_eq!(fighter.ability_scores.total(), 6 * u32::from(rules.base) + u32::from(rules.points));
+            assert!(sorted.iter().all(|score| (rules.base..=rules.max).contains(score)));
+            assert_eq!(fighter.ability_scores.strength, sorted[5]);
+        }
+
+        // Every point fits only with every score at the max
+        let full = PointBuy { base: 8, max: 10, points: 12 };
+        assert_eq!(full.spend(&mut seeded_rng(0)), [10; 6]);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let rules = AbilityRules {
+            standard_array: vec![15, 14, 13],
+            ..AbilityRules::default()
+        };
+        assert_eq!(rules.validate().unwrap_err().to_string(), "standard_array needs 6 scores, not 3");
+
+        let rules = AbilityRules {
+            point_buy: PointBuy { base: 8, max: 10, points: 13 },
+            ..AbilityRules::default()
+        };
+        assert_eq!(
+            rules.validate().unwrap_err().to_string(),
+            "point_buy has 13 points but only 12 can be spent between 8 and 10"
+        );
+
+        let mut config = generator(AbilityMethod::InOrder).get_config().clone();
+        config.classes[0].ability_priority = vec![Ability::Strength, Ability::Wisdom, Ability::Strength];
+        let err = CharacterGenerator::validate_config(&config).unwrap_err();
+        assert_eq!(err.to_string(), "Class Fighter lists strength more than once in ability_priority");
+    }
+
+    #[test]
+    fn test_unchecked_configs_error_instead_of_panicking() {
+        // `from_config` skips validation, so bad rules reach generation
+        let mut config = generator(AbilityMethod::InOrder).get_config().clone();
+        config.abilities.standard_array.pop();
+        let err = CharacterGenerator::from_config(config.clone())
+            .with_ability_method(AbilityMethod::StandardArray)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Invalid ability rules: standard_array needs 6 scores, not 5");
+
+        config.abilities.method = AbilityMethod::StandardArray;
+        let generator_from_config = CharacterGenerator::from_config(config);
+        let err = generator_from_config
+            .generate_character(&mut seeded_rng(0), 1, &RollSpec::default(), &Constraints::default())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid ability rules: standard_array needs 6 scores, not 5");
+
+        let mut config = generator(AbilityMethod::InOrder).get_config().clone();
+        config.classes[0].ability_priority = vec![Ability::Strength, Ability::Strength];
+        assert!(CharacterGenerator::from_config(config.clone())
+            .with_ability_method(AbilityMethod::Arrange)
+            .is_err());
+
+        config.abilities.method = AbilityMethod::Arrange;
+        let err = generate_result(&CharacterGenerator::from_config(config), "Fighter", 0).unwrap_err();
+        assert_eq!(err.to_string(), "ability_priority lists strength more than once");
+    }
+}
@@ -1,32 +1,129 @@
-use glog_v2_character_generator::{CharacterGenerator, save_characters_to_file};
-use clap::Parser;
+use glog_v2_character_generator::abilities::AbilityMethod;
+use glog_v2_character_generator::dice::RollSpec;
+use glog_v2_character_generator::funnel::save_funnel_to_file;
+use glog_v2_character_generator::levels::LevelSpec;
+use glog_v2_character_generator::multiclass::MulticlassPolicy;
+use glog_v2_character_generator::party::PartyRules;
+use glog_v2_character_generator::{Character, CharacterGenerator, Constraints, DEFAULT_METHOD, MAX_SEED, load_characters_file, random_seed, save_characters_to_file, seeded_rng, write_characters_file};
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct CharacterArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     // config file to use
-    #[arg(long, default_value = "config.toml")]
+    #[arg(long, default_value = "config.toml", global = true)]
     config: String,
 
-    // level
-    #[arg(short, long, default_value_t = 1)]
-    level: u8,
+    // level, range or weighted levels, e.g. "2", "1-3" or "1:3,2:1"
+    #[arg(short, long, default_value = "1")]
+    level: LevelSpec,
 
-    // number of dice for stats
-    #[arg(short, long, default_value_t = 3)]
-    dice: u8,
+    // dice expression for each ability score, e.g. "3d6" or "4d6kh3"
+    #[arg(short, long, default_value = DEFAULT_METHOD)]
+    method: RollSpec,
 
-    // number of faces for dice
-    #[arg(short, long, default_value_t = 6)]
-    faces: u8,
+    // how ability scores are assigned: in-order, arrange, point-buy or
+    // standard-array (the config's method if omitted)
+    #[arg(long)]
+    ability_method: Option<AbilityMethod>,
 
-    // how many characters to create
+    // how many characters to create (for each player in a funnel)
     #[arg(short, long, default_value_t = 1)]
     count: u8,
 
-    // how many low rolls should be ignored
-    #[arg(long, default_value_t = 0)]
-    lowest: u8,
+    // only generate these species (repeatable)
+    #[arg(long = "species")]
+    species: Vec<String>,
+
+    // never generate these species (repeatable)
+    #[arg(long = "exclude-species")]
+    exclude_species: Vec<String>,
+
+    // only generate these classes (repeatable)
+    #[arg(long = "class")]
+    classes: Vec<String>,
+
+    // never generate these classes (repeatable)
+    #[arg(long = "exclude-class")]
+    exclude_classes: Vec<String>,
+
+    // only generate these subclasses, e.g. wizard archetypes (repeatable)
+    #[arg(long = "subclass")]
+    subclasses: Vec<String>,
+
+    // never generate these subclasses (repeatable)
+    #[arg(long = "exclude-subclass")]
+    exclude_subclasses: Vec<String>,
+
+    // name to give a character instead of a generated one, in order
+    // (repeatable)
+    #[arg(long = "name")]
+    names: Vec<String>,
+
+    // generate classless level 0 characters for a funnel, grouped by player
+    #[arg(long, conflicts_with_all = ["party", "level", "classes", "exclude_classes", "subclasses", "exclude_subclasses"])]
+    funnel: bool,
+
+    // how many players share the funnel
+    #[arg(long, requires = "funnel", default_value_t = 1)]
+    players: u8,
+
+    // generate the characters as one party, following the party rules below
+    #[arg(long)]
+    party: bool,
+
+    // party rule: no two members share a class
+    #[arg(long, requires = "party")]
+    unique_classes: bool,
+
+    // party rule: include a member of this class (repeatable)
+    #[arg(long = "require-class", requires = "party")]
+    required_classes: Vec<String>,
+
+    // party rule: most members that can share a species
+    #[arg(long, requires = "party", value_parser = clap::value_parser!(u8).range(1..))]
+    max_per_species: Option<u8>,
+
+    // party rule: lowest total of all members' ability scores
+    #[arg(long, requires = "party")]
+    min_ability_total: Option<u32>,
+
+    // party rule: highest total of all members' ability scores
+    #[arg(long, requires = "party")]
+    max_ability_total: Option<u32>,
+
+    // chance of taking each template after the first from a new class,
+    // 0 to 1 (the config's multiclass policy if omitted)
+    #[arg(long, global = true)]
+    multiclass_chance: Option<f64>,
+
+    // most classes one character can have
+    #[arg(long, global = true, value_parser = clap::value_parser!(u8).range(1..))]
+    max_classes: Option<u8>,
+
+    // campaign setting whose frequency table to use
+    #[arg(long)]
+    setting: Option<String>,
+
+    // seed for reproducible generation (random if omitted)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(..=MAX_SEED), global = true)]
+    seed: Option<u64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    // level up characters from a saved characters file and write them back
+    LevelUp {
+        // characters file saved by an earlier run
+        file: String,
+
+        // only level up this character, counting from 1 (all if omitted)
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        character: Option<u64>,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
@@ -35,30 +132,216 @@ fn main() -> anyhow::Result<()> {
     
     let args = CharacterArgs::parse();
     // Initialize the character generator
-    let generator = CharacterGenerator::new(&args.config)?;
+    let mut generator = CharacterGenerator::new(&args.config)?;
+    if args.multiclass_chance.is_some() || args.max_classes.is_some() {
+        let configured = &generator.get_config().multiclass;
+        let policy = MulticlassPolicy {
+            chance: args.multiclass_chance.unwrap_or(configured.chance),
+            max_classes: args.max_classes.unwrap_or(configured.max_classes),
+        };
+        generator = generator.with_multiclass(policy)?;
+    }
+    if let Some(Command::LevelUp { file, character }) = &args.command {
+        return level_up(&generator, file, *character, args.seed);
+    }
+    if let Some(setting) = &args.setting {
+        generator = generator.with_setting(setting)?;
+    }
+    if let Some(method) = args.ability_method {
+        generator = generator.with_ability_method(method)?;
+    }
+    let method = generator.get_config().abilities.method.describe(&args.method);
+    
+    let constraints = Constraints {
+        species: args.species,
+        exclude_species: args.exclude_species,
+        classes: args.classes,
+        exclude_classes: args.exclude_classes,
+        subclasses: args.subclasses,
+        exclude_subclasses: args.exclude_subclasses,
+    };
     
     // Generate characters
-    let characters = generator.generate_characters(args.level, args.count, args.dice, args.faces, args.lowest)?;
+    let seed = args.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+    let total = if args.funnel { usize::from(args.players) * usize::from(args.count) } else { usize::from(args.count) };
+    if args.names.len() > total {
+        return Err(anyhow::anyhow!("Got {} names for only {} character(s)", args.names.len(), total));
+    }
+    
+    if args.funnel {
+        let mut players = generator.generate_funnel(&mut rng, args.players, args.count, &args.method, &constraints)?;
+        for (character, name) in players.iter_mut().flat_map(|player| &mut player.characters).zip(args.names) {
+            character.name = Some(name);
+        }
+        
+        println!("Seed: {}", seed);
+        println!("Method: {}", method);
+        println!("Funnel: {} player(s), {} character(s) each", args.players, args.count);
+        for player in &players {
+            println!("\n=== Player {} ===", player.player);
+            for (i, character) in player.characters.iter().enumerate() {
+                print_character(i + 1, character);
+            }
+        }
+        
+        let filename = save_funnel_to_file(&players, seed)?;
+        println!("\n{} funnel character(s) generated successfully!", total);
+        println!("Characters saved to: {}", filename);
+        return Ok(());
+    }
+    let mut party_total = None;
+    let mut characters = if args.party {
+        let rules = PartyRules {
+            unique_classes: args.unique_classes,
+            required_classes: args.required_classes,
+            max_per_species: args.max_per_species,
+            min_ability_total: args.min_ability_total,
+            max_ability_total: args.max_ability_total,
+        };
+        let party = generator.generate_party(&mut rng, &args.level, args.count, &args.method, &constraints, &rules)?;
+        party_total = Some(party.ability_total());
+        party.members
+    } else {
+        generator.generate_characters(&mut rng, &args.level, args.count, &args.method, &constraints)?
+    };
+    for (character, name) in characters.iter_mut().zip(args.names) {
+        character.name = Some(name);
+    }
+    
+    println!("Seed: {}", seed);
+    println!("Method: {}", method);
+    println!("Levels: {}", args.level);
+    if let Some(setting) = &args.setting {
+        println!("Setting: {}", setting);
+    }
     
     // Display characters
     for (i, character) in characters.iter().enumerate() {
-        println!("\nCharacter {}:", i + 1);
-        println!("Level: {}", character.level);
-        println!("Species: {}", character.species);
-        println!("Class: {}", character.class);
-        println!("Ability Scores:");
-        println!("  Strength: {}", character.ability_scores.strength);
-        println!("  Dexterity: {}", character.ability_scores.dexterity);
-        println!("  Constitution: {}", character.ability_scores.constitution);
-        println!("  Intelligence: {}", character.ability_scores.intelligence);
-        println!("  Wisdom: {}", character.ability_scores.wisdom);
-        println!("  Charisma: {}", character.ability_scores.charisma);
+        print_character(i + 1, character);
+    }
+    
+    if let Some(total) = party_total {
+        println!("\nParty ability total: {}", total);
     }
     
     // Save to file
-    let filename = save_characters_to_file(&characters, args.level, args.count)?;
+    let filename = save_characters_to_file(&characters, seed)?;
     println!("\n{} character(s) generated successfully!", args.count);
     println!("Characters saved to: {}", filename);
     
     Ok(())
 }
+
+fn print_character(number: usize, character: &Character) {
+    println!("\nCharacter {}:", number);
+    if let Some(name) = &character.name {
+        println!("Name: {}", name);
+    }
+    println!("Level: {}", character.level);
+    println!("Species: {}", character.species);
+    if !character.traits.is_empty() {
+        println!("Traits: {}", character.traits.join("; "));
+    }
+    if let Some(quirk) = &character.quirk {
+        println!("Quirk: {}", quirk);
+    }
+    if !character.classes.is_empty() {
+        println!("Class: {}", character.class_name());
+        println!("Templates: {}", character.templates().join(", "));
+    }
+    if let Some(occupation) = &character.occupation {
+        println!("Occupation: {}", occupation);
+    }
+    if let Some(background) = &character.background {
+        println!("Background: {} (skill: {})", background.name, background.skill);
+    }
+    println!("Hit Points: {}", character.hit_points);
+    println!("Ability Scores:");
+    println!("  Strength: {}", character.ability_scores.strength);
+    println!("  Dexterity: {}", character.ability_scores.dexterity);
+    println!("  Constitution: {}", character.ability_scores.constitution);
+    println!("  Intelligence: {}", character.ability_scores.intelligence);
+    println!("  Wisdom: {}", character.ability_scores.wisdom);
+    println!("  Charisma: {}", character.ability_scores.charisma);
+    println!("Derived Stats:");
+    println!("  Attack: {}", character.derived_stats.attack);
+    println!("  Defense: {}", character.derived_stats.defense);
+    println!("  Movement: {}", character.derived_stats.movement);
+    println!("  Stealth: {}", character.derived_stats.stealth);
+    println!("  Save: {}", character.derived_stats.save);
+    println!("  Inventory Slots: {}", character.inventory.capacity);
+    if !character.features.is_empty() {
+        println!("Features:");
+        for feature in &character.features {
+            println!("  [{} {}] {}: {}", feature.class, feature.template, feature.name, feature.text);
+        }
+    }
+    if let Some(magic) = &character.magic {
+        println!("Magic Dice: {}", magic.magic_dice);
+        println!("Spells:");
+        for spell in &magic.spells {
+            if spell.text.is_empty() {
+                println!("  {}", spell.name);
+            } else {
+                println!("  {}: {}", spell.name, spell.text);
+            }
+        }
+        for (heading, entries) in [("Cantrips", &magic.cantrips), ("Perks", &magic.perks), ("Drawbacks", &magic.drawbacks)] {
+            if !entries.is_empty() {
+                println!("{}:", heading);
+                for entry in entries {
+                    println!("  {}", entry);
+                }
+            }
+        }
+    }
+    let inventory = &character.inventory;
+    println!("Inventory ({}/{} slots):", inventory.used_slots(), inventory.capacity);
+    for item in &inventory.quick_draw {
+        println!("  [Quick Draw] {}", item);
+    }
+    for item in &inventory.pack {
+        println!("  {}", item);
+    }
+    if inventory.is_over_capacity() {
+        println!("  Encumbered: -{0} Stealth, -{0} Movement", inventory.encumbrance());
+    }
+}
+
+// Level up the chosen character, or all of them, and save them back to
+// the file they came from
+fn level_up(generator: &CharacterGenerator, filename: &str, only: Option<u64>, seed: Option<u64>) -> anyhow::Result<()> {
+    let mut file = load_characters_file(filename)?;
+    if let Some(number) = only {
+        if number > file.characters.len() as u64 {
+            return Err(anyhow::anyhow!("{} only has {} character(s)", filename, file.characters.len()));
+        }
+    }
+    
+    // Every character is levelled before anything is shown or saved, so a
+    // failure part way through leaves the file as it was
+    let seed = seed.unwrap_or_else(random_seed);
+    let gains = generator.level_up_file(&mut file, seed, only)?;
+    println!("Seed: {}", seed);
+    
+    for (number, gained) in &gains {
+        print_character(*number, &file.characters[number - 1]);
+        println!("Gained at level {}:", gained.level);
+        println!("  Hit Points: +{}", gained.hit_points);
+        for feature in &gained.features {
+            println!("  [{} {}] {}", feature.class, feature.template, feature.name);
+        }
+        if gained.magic_dice > 0 {
+            println!("  Magic Dice: +{}", gained.magic_dice);
+        }
+        for spell in &gained.spells {
+            println!("  Spell: {}", spell.name);
+        }
+    }
+    
+    write_characters_file(filename, &file)?;
+    println!("\nCharacters saved to: {}", filename);
+    
+    Ok(())
+}
@@ -1,33 +1,71 @@
-use glog_v2_character_generator::{CharacterGenerator};
+use glog_v2_character_generator::dice::RollSpec;
+use glog_v2_character_generator::magic::{resolve_magic_dice, roll_magic_dice, CastOutcome};
+use glog_v2_character_generator::{CharacterGenerator, DEFAULT_METHOD, MAX_SEED, random_seed, seeded_rng};
 use clap::Parser;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct StatArgs {
     // number of stats to roll
-    #[arg(short, long, default_value_t = 6)]
+    #[arg(short, long, default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..))]
     stats: u8,
 
-    // number of dice to roll per stat
-    #[arg(short, long, default_value_t = 3)]
-    dice: u8,
+    // dice expression to roll per stat, e.g. "3d6" or "4d6kh3"
+    #[arg(short, long, default_value = DEFAULT_METHOD)]
+    method: RollSpec,
 
-    // number of faces per die
-    #[arg(short, long, default_value_t = 6)]
-    faces: u8,
+    // seed for reproducible rolls (random if omitted)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(..=MAX_SEED))]
+    seed: Option<u64>,
 
-    // how many low rolls should be ignored
-    #[arg(short, long, default_value_t = 0)]
-    lowest: u8,
+    // config file to read archetype mishap and doom tables from
+    #[arg(long, default_value = "config.toml")]
+    config: String,
+
+    // number of Magic Dice to roll instead of an ability score
+    #[arg(long, requires = "archetype", value_parser = clap::value_parser!(u8).range(1..))]
+    magic_dice: Option<u8>,
+
+    // wizard archetype whose mishap and doom tables apply
+    #[arg(long, requires = "magic_dice")]
+    archetype: Option<String>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let args = StatArgs::parse();
-    let mut rng = rand::thread_rng();
-    let result = CharacterGenerator::roll_ability_score(&mut rng, args.dice, args.faces, args.lowest);
+    let seed = args.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+
+    if let (Some(count), Some(archetype)) = (args.magic_dice, &args.archetype) {
+        let generator = CharacterGenerator::new(&args.config)?;
+        let subclass = generator
+            .get_config()
+            .subclass(archetype)
+            .ok_or_else(|| anyhow::anyhow!("Unknown archetype: {}", archetype))?;
+
+        let dice = roll_magic_dice(&mut rng, count);
+        let casting = resolve_magic_dice(&mut rng, subclass, &dice)?;
+        let dice: Vec<String> = casting.dice.iter().map(u8::to_string).collect();
+
+        println!("Seed: {}", seed);
+        println!("Magic Dice ({}): {}", subclass.name, dice.join(", "));
+        println!("Sum: {}", casting.sum);
+        println!("Returned to pool: {}", casting.returned);
+        match casting.outcome {
+            CastOutcome::Normal => {}
+            CastOutcome::Mishap(mishap) => println!("Mishap: {}", mishap),
+            CastOutcome::Doom(doom) => println!("Doom: {}", doom),
+        }
+        return Ok(());
+    }
+
+    let results: Vec<String> = (0..args.stats)
+        .map(|_| CharacterGenerator::roll_ability_score(&mut rng, &args.method).to_string())
+        .collect();
 
-    println!("Roll {}d{} drop {} lowest: {}", args.dice, args.faces, args.lowest, result);
+    println!("Seed: {}", seed);
+    println!("Roll {}: {}", args.method, results.join(", "));
 
     Ok(())
 }
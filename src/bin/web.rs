@@ -5,22 +5,81 @@ use axum::{
     routing::{get, post},
     Router,
 };
-use glog_v2_character_generator::{CharacterGenerator, Character};
-use serde::{Deserialize, Serialize};
+use glog_v2_character_generator::abilities::AbilityMethod;
+use glog_v2_character_generator::dice::RollSpec;
+use glog_v2_character_generator::funnel::MAX_FUNNEL_PLAYERS;
+use glog_v2_character_generator::level_up::LevelUp;
+use glog_v2_character_generator::levels::LevelSpec;
+use glog_v2_character_generator::party::PartyRules;
+use glog_v2_character_generator::{CharacterGenerator, Character, Constraints, DEFAULT_METHOD, MAX_LEVEL, MAX_SEED, MIN_LEVEL, random_seed, seeded_rng};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::sync::Arc;
 use tower_http::services::ServeDir;
 
 type AppState = Arc<CharacterGenerator>;
 
+// Keep web requests smaller than the library's batch limit
+const MAX_WEB_COUNT: u32 = 20;
+
 #[derive(Debug, Deserialize)]
 struct GenerateRequest {
-    level: u32,
+    // Level, range or weighted levels; plain numbers are accepted too
+    #[serde(deserialize_with = "number_or_string")]
+    level: String,
     count: u32,
+    seed: Option<u64>,
+    method: Option<String>,
+    // in-order, arrange, point-buy or standard-array; the config's if omitted
+    ability_method: Option<String>,
+    #[serde(flatten)]
+    constraints: Constraints,
+    // Generate the characters as one party following these rules
+    party: Option<PartyRules>,
+    // Generate a level 0 funnel instead, `count` characters for each of this
+    // many players
+    funnel_players: Option<u8>,
+}
+
+fn number_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(u64),
+        Text(String),
+    }
+    
+    Ok(match Raw::deserialize(deserializer)? {
+        Raw::Number(number) => number.to_string(),
+        Raw::Text(text) => text,
+    })
 }
 
 #[derive(Debug, Serialize)]
 struct GenerateResponse {
     characters: Vec<Character>,
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    party_ability_total: Option<u32>,
+    // For a funnel, the player each character belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    players: Option<Vec<u8>>,
+    success: bool,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LevelUpRequest {
+    character: Character,
+    // Seed for the level-up's rolls, random if omitted; the one used is
+    // always sent back
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct LevelUpResponse {
+    character: Option<Character>,
+    gained: Option<LevelUp>,
+    seed: Option<u64>,
     success: bool,
     message: String,
 }
@@ -35,6 +94,7 @@ async fn main() -> anyhow::Result<()> {
     let app = Router::new()
         .route("/", get(serve_index))
         .route("/generate", post(generate_characters))
+        .route("/level-up", post(level_up_character))
         .nest_service("/static", ServeDir::new("static"))
         .with_state(app_state);
 
@@ -87,6 +147,12 @@ async fn serve_index() -> Html<String> {
             margin-bottom: 20px;
         }
         
+        .form-grid {
+            display: grid;
+            grid-template-columns: repeat(2, 1fr);
+            column-gap: 15px;
+        }
+        
         label {
             display: block;
             margin-bottom: 8px;
@@ -94,7 +160,7 @@ async fn serve_index() -> Html<String> {
             color: #2d3748;
         }
         
-        input {
+        input, select {
             width: 100%;
             padding: 12px;
             border: 2px solid #e2e8f0;
@@ -103,6 +169,11 @@ async fn serve_index() -> Html<String> {
             transition: border-color 0.3s;
         }
         
+        input[type="checkbox"] {
+            width: auto;
+            margin-right: 8px;
+        }
+        
         input:focus {
             outline: none;
             border-color: #667eea;
@@ -133,6 +204,13 @@ async fn serve_index() -> Html<String> {
             transform: none;
         }
         
+        .level-up-btn {
+            width: auto;
+            padding: 8px 16px;
+            font-size: 14px;
+            margin-top: 15px;
+        }
+        
         .character {
             background: #f7fafc;
             border: 2px solid #e2e8f0;
@@ -195,6 +273,18 @@ async fn serve_index() -> Html<String> {
             color: #2d3748;
         }
         
+        .features {
+            background: white;
+            border-radius: 6px;
+            border: 1px solid #e2e8f0;
+            padding: 10px 10px 10px 30px;
+            margin: 0;
+        }
+        
+        .features li {
+            margin-bottom: 6px;
+        }
+        
         .loading {
             text-align: center;
             color: #667eea;
@@ -224,13 +314,99 @@ async fn serve_index() -> Html<String> {
         
         <form id="characterForm">
             <div class="form-group">
-                <label for="level">Character Level (1-10):</label>
-                <input type="number" id="level" name="level" min="1" max="20" value="1" required>
+                <label for="level">Character Level ({{MIN_LEVEL}}-{{MAX_LEVEL}}):</label>
+                <input type="text" id="level" name="level" value="{{MIN_LEVEL}}" placeholder="e.g. 2, 1-3 or 1:3,2:1" required>
             </div>
             
             <div class="form-group">
                 <label for="count">Number of Characters:</label>
-                <input type="number" id="count" name="count" min="1" max="20" value="1" required>
+                <input type="number" id="count" name="count" min="1" max="{{MAX_WEB_COUNT}}" value="1" required>
+            </div>
+            
+            <div class="form-group">
+                <label for="method">Ability Score Method (e.g. 3d6, 4d6kh3, 3d6r1):</label>
+                <input type="text" id="method" name="method" value="{{DEFAULT_METHOD}}" required>
+            </div>
+            
+            <div class="form-group">
+                <label for="ability_method">Ability Score Assignment:</label>
+                <select id="ability_method" name="ability_method">
+                    <option value="">Config default</option>
+                    <option value="in-order">Roll in order</option>
+                    <option value="arrange">Roll and arrange by class</option>
+                    <option value="point-buy">Point-buy</option>
+                    <option value="standard-array">Standard array</option>
+                </select>
+            </div>
+            
+            <div class="form-group">
+                <label for="seed">Seed (optional):</label>
+                <input type="number" id="seed" name="seed" min="0" placeholder="Random">
+            </div>
+            
+            <div class="form-group">
+                <label for="level_up_seed">Level Up Seed (optional):</label>
+                <input type="number" id="level_up_seed" name="level_up_seed" min="0" placeholder="Random">
+            </div>
+            
+            <div class="form-grid">
+                <div class="form-group">
+                    <label for="species">Only Species:</label>
+                    <input type="text" id="species" name="species" placeholder="Any (comma-separated)">
+                </div>
+                <div class="form-group">
+                    <label for="exclude_species">Exclude Species:</label>
+                    <input type="text" id="exclude_species" name="exclude_species" placeholder="None (comma-separated)">
+                </div>
+                <div class="form-group">
+                    <label for="classes">Only Classes:</label>
+                    <input type="text" id="classes" name="classes" placeholder="Any (comma-separated)">
+                </div>
+                <div class="form-group">
+                    <label for="exclude_classes">Exclude Classes:</label>
+                    <input type="text" id="exclude_classes" name="exclude_classes" placeholder="None (comma-separated)">
+                </div>
+                <div class="form-group">
+                    <label for="subclasses">Only Subclasses:</label>
+                    <input type="text" id="subclasses" name="subclasses" placeholder="Any (comma-separated)">
+                </div>
+                <div class="form-group">
+                    <label for="exclude_subclasses">Exclude Subclasses:</label>
+                    <input type="text" id="exclude_subclasses" name="exclude_subclasses" placeholder="None (comma-separated)">
+                </div>
+            </div>
+            
+            <div class="form-group">
+                <label><input type="checkbox" id="party" name="party">Generate as a party</label>
+            </div>
+            
+            <div class="form-grid">
+                <div class="form-group">
+                    <label><input type="checkbox" id="unique_classes" name="unique_classes">No repeated classes</label>
+                </div>
+                <div class="form-group">
+                    <label for="required_classes">Required Classes:</label>
+                    <input type="text" id="required_classes" name="required_classes" placeholder="None (comma-separated)">
+                </div>
+                <div class="form-group">
+                    <label for="max_per_species">Most of One Species:</label>
+                    <input type="number" id="max_per_species" name="max_per_species" min="1" placeholder="Any">
+                </div>
+                <div class="form-group">
+                    <label for="ability_total">Party Ability Total (min / max):</label>
+                    <input type="number" id="min_ability_total" name="min_ability_total" min="0" placeholder="Any">
+                    <input type="number" id="max_ability_total" name="max_ability_total" min="0" placeholder="Any">
+                </div>
+            </div>
+            
+            <div class="form-grid">
+                <div class="form-group">
+                    <label><input type="checkbox" id="funnel" name="funnel">Level 0 funnel (Number of Characters is per player)</label>
+                </div>
+                <div class="form-group">
+                    <label for="funnel_players">Funnel Players:</label>
+                    <input type="number" id="funnel_players" name="funnel_players" min="1" max="{{MAX_FUNNEL_PLAYERS}}" value="1">
+                </div>
             </div>
             
             <button type="submit" id="generateBtn">Generate Characters</button>
@@ -249,17 +425,15 @@ async fn serve_index() -> Html<String> {
             
             const level = document.getElementById('level').value;
             const count = document.getElementById('count').value;
+            const seed = document.getElementById('seed').value;
+            const method = document.getElementById('method').value;
+            const abilityMethod = document.getElementById('ability_method').value;
             const generateBtn = document.getElementById('generateBtn');
             const results = document.getElementById('results');
             
-            // Validation
-            if (level < 1 || level > 20) {
-                showError('Level must be between 1 and 20');
-                return;
-            }
-            
-            if (count < 1 || count > 20) {
-                showError('Number of characters must be between 1 and 20');
+            // Validation (level ranges are checked by the server)
+            if (count < 1 || count > {{MAX_WEB_COUNT}}) {
+                showError('Number of characters must be between 1 and {{MAX_WEB_COUNT}}');
                 return;
             }
             
@@ -275,15 +449,27 @@ async fn serve_index() -> Html<String> {
                         'Content-Type': 'application/json',
                     },
                     body: JSON.stringify({
-                        level: parseInt(level),
-                        count: parseInt(count)
+                        level: level,
+                        count: parseInt(count),
+                        seed: seed === '' ? null : parseInt(seed),
+                        method: method,
+                        ability_method: abilityMethod === '' ? null : abilityMethod,
+                        species: listField('species'),
+                        exclude_species: listField('exclude_species'),
+                        classes: listField('classes'),
+                        exclude_classes: listField('exclude_classes'),
+                        subclasses: listField('subclasses'),
+                        exclude_subclasses: listField('exclude_subclasses'),
+                        party: partyRules(),
+                        funnel_players: funnelPlayers()
                     })
                 });
                 
                 const data = await response.json();
                 
                 if (data.success) {
-                    displayCharacters(data.characters);
+                    shown = { characters: data.characters, seed: data.seed, partyAbilityTotal: data.party_ability_total, players: data.players, levelUpSeeds: {} };
+                    displayCharacters(data.characters, data.seed, data.party_ability_total, data.players);
                 } else {
                     showError(data.message);
                 }
@@ -295,24 +481,77 @@ async fn serve_index() -> Html<String> {
             }
         });
         
-        function displayCharacters(characters) {
+        // The characters on screen, kept so they can be levelled up
+        let shown = null;
+        
+        async function levelUp(index) {
+            const levelUpSeed = document.getElementById('level_up_seed').value;
+            const response = await fetch('/level-up', {
+                method: 'POST',
+                headers: {
+                    'Content-Type': 'application/json',
+                },
+                body: JSON.stringify({ character: shown.characters[index], seed: levelUpSeed === '' ? null : parseInt(levelUpSeed) })
+            });
+            const data = await response.json();
+            
+            if (data.success) {
+                shown.characters[index] = data.character;
+                shown.levelUpSeeds[index] = [...(shown.levelUpSeeds[index] || []), data.seed];
+            }
+            displayCharacters(shown.characters, shown.seed, shown.partyAbilityTotal, shown.players);
+            if (!data.success) {
+                document.getElementById('results').insertAdjacentHTML('afterbegin', `
+                    <div class="error">
+                        <strong>Error:</strong> ${escape(data.message)}
+                    </div>
+                `);
+            }
+        }
+        
+        function displayCharacters(characters, seed, partyAbilityTotal, players) {
             const results = document.getElementById('results');
-            let html = '';
+            let html = `<div class="info-item"><strong>Seed:</strong> ${seed}</div>`;
+            if (partyAbilityTotal !== undefined) {
+                html += `<div class="info-item"><strong>Party Ability Total:</strong> ${partyAbilityTotal}</div>`;
+            }
             
             characters.forEach((character, index) => {
+                if (players && players[index] !== players[index - 1]) {
+                    html += `<h2>Player ${players[index]}</h2>`;
+                }
                 html += `
                     <div class="character">
-                        <h3>Character ${index + 1}</h3>
+                        <h3>${escape(character.name || `Character ${index + 1}`)}</h3>
                         <div class="character-info">
                             <div class="info-item">
                                 <strong>Level:</strong> ${character.level}
                             </div>
                             <div class="info-item">
-                                <strong>Race:</strong> ${character.race}
+                                <strong>Species:</strong> ${escape(character.species)}
                             </div>
+                            ${character.classes.length > 0 ? `
                             <div class="info-item">
-                                <strong>Class:</strong> ${character.class}
+                                <strong>Class:</strong> ${escape(className(character.classes))}
                             </div>
+                            <div class="info-item">
+                                <strong>Templates:</strong> ${escape(templateList(character.classes))}
+                            </div>` : ''}
+                            ${character.occupation ? `
+                            <div class="info-item">
+                                <strong>Occupation:</strong> ${escape(character.occupation)}
+                            </div>` : ''}
+                            ${character.background ? `
+                            <div class="info-item">
+                                <strong>Background:</strong> ${escape(character.background.name)} (${escape(character.background.skill)})
+                            </div>` : ''}
+                            <div class="info-item">
+                                <strong>HP:</strong> ${character.hit_points}
+                            </div>
+                            ${shown.levelUpSeeds[index] ? `
+                            <div class="info-item">
+                                <strong>Level Up Seeds:</strong> ${shown.levelUpSeeds[index].join(', ')}
+                            </div>` : ''}
                         </div>
                         <h4>Ability Scores:</h4>
                         <div class="ability-scores">
@@ -341,6 +580,38 @@ async fn serve_index() -> Html<String> {
                                 <div class="ability-score">${character.ability_scores.charisma}</div>
                             </div>
                         </div>
+                        <h4>Derived Stats:</h4>
+                        <div class="ability-scores">
+                            <div class="ability">
+                                <div class="ability-name">Attack</div>
+                                <div class="ability-score">${character.derived_stats.attack}</div>
+                            </div>
+                            <div class="ability">
+                                <div class="ability-name">Defense</div>
+                                <div class="ability-score">${character.derived_stats.defense}</div>
+                            </div>
+                            <div class="ability">
+                                <div class="ability-name">Movement</div>
+                                <div class="ability-score">${character.derived_stats.movement}</div>
+                            </div>
+                            <div class="ability">
+                                <div class="ability-name">Stealth</div>
+                                <div class="ability-score">${character.derived_stats.stealth}</div>
+                            </div>
+                            <div class="ability">
+                                <div class="ability-name">Save</div>
+                                <div class="ability-score">${character.derived_stats.save}</div>
+                            </div>
+                            <div class="ability">
+                                <div class="ability-name">Slots</div>
+                                <div class="ability-score">${character.inventory.capacity}</div>
+                            </div>
+                        </div>
+                        ${renderTraits(character)}
+                        ${renderFeatures(character.features)}
+                        ${renderMagic(character.magic)}
+                        ${renderInventory(character.inventory)}
+                        <button type="button" class="level-up-btn" onclick="levelUp(${index})" ${character.level >= {{MAX_LEVEL}} ? 'disabled' : ''}>Level Up</button>
                     </div>
                 `;
             });
@@ -348,10 +619,130 @@ async fn serve_index() -> Html<String> {
             document.getElementById('results').innerHTML = html;
         }
         
+        function partyRules() {
+            if (!document.getElementById('party').checked) {
+                return null;
+            }
+            
+            const number = id => {
+                const value = document.getElementById(id).value;
+                return value === '' ? null : parseInt(value);
+            };
+            return {
+                unique_classes: document.getElementById('unique_classes').checked,
+                required_classes: listField('required_classes'),
+                max_per_species: number('max_per_species'),
+                min_ability_total: number('min_ability_total'),
+                max_ability_total: number('max_ability_total')
+            };
+        }
+        
+        function funnelPlayers() {
+            if (!document.getElementById('funnel').checked) {
+                return null;
+            }
+            
+            return parseInt(document.getElementById('funnel_players').value);
+        }
+        
+        function listField(id) {
+            return document.getElementById(id).value
+                .split(',')
+                .map(name => name.trim())
+                .filter(name => name !== '');
+        }
+        
+        // e.g. "Fighter / Wizard (Necromancer)"
+        function className(classes) {
+            return classes
+                .map(c => c.subclass ? `${c.name} (${c.subclass})` : c.name)
+                .join(' / ');
+        }
+        
+        // e.g. "Fighter A, Fighter B, Wizard A"
+        function templateList(classes) {
+            return classes
+                .flatMap(c => ['A', 'B', 'C', 'D'].slice(0, c.templates).map(t => `${c.name} ${t}`))
+                .join(', ');
+        }
+        
+        function renderTraits(character) {
+            const traits = (character.traits || []).map(escape);
+            if (character.quirk) {
+                traits.push(`<strong>Quirk:</strong> ${escape(character.quirk)}`);
+            }
+            if (traits.length === 0) {
+                return '';
+            }
+            
+            return `<h4>Traits:</h4><ul class="features">${traits.map(t => `<li>${t}</li>`).join('')}</ul>`;
+        }
+        
+        function renderFeatures(features) {
+            if (features.length === 0) {
+                return '';
+            }
+            
+            const items = features
+                .map(f => `<li><strong>[${escape(f.class)} ${escape(f.template)}] ${escape(f.name)}:</strong> ${escape(f.text)}</li>`)
+                .join('');
+            return `<h4>Features:</h4><ul class="features">${items}</ul>`;
+        }
+        
+        function renderMagic(magic) {
+            if (!magic) {
+                return '';
+            }
+            
+            const list = (title, entries) => entries.length === 0 ? '' :
+                `<h4>${title}:</h4><ul class="features">${entries.map(e => `<li>${e}</li>`).join('')}</ul>`;
+            const spells = magic.spells.map(s => s.text ? `<strong>${escape(s.name)}:</strong> ${escape(s.text)}` : escape(s.name));
+            
+            return `
+                <h4>Magic Dice: ${magic.magic_dice}</h4>
+                ${list('Spells', spells)}
+                ${list('Cantrips', magic.cantrips.map(escape))}
+                ${list('Perks', magic.perks.map(escape))}
+                ${list('Drawbacks', magic.drawbacks.map(escape))}
+            `;
+        }
+        
+        function renderInventory(inventory) {
+            const describe = item => {
+                const details = [];
+                if (item.slots !== 1) details.push(`${item.slots} slots`);
+                if (item.armor) details.push(`+${item.armor} armor`);
+                if (item.damage) details.push(`${item.damage} damage`);
+                return escape(`${item.name}${details.length ? ` (${details.join(', ')})` : ''}`);
+            };
+            const used = inventory.pack.reduce((total, item) => total + item.slots, 0);
+            const encumbrance = Math.max(0, used - inventory.capacity);
+            
+            const items = [
+                ...inventory.quick_draw.map(item => `<li><strong>[Quick Draw]</strong> ${describe(item)}</li>`),
+                ...inventory.pack.map(item => `<li>${describe(item)}</li>`),
+            ];
+            if (encumbrance > 0) {
+                items.push(`<li><strong>Encumbered:</strong> -${encumbrance} Stealth, -${encumbrance} Movement</li>`);
+            }
+            return `<h4>Inventory (${used}/${inventory.capacity} slots):</h4><ul class="features">${items.join('')}</ul>`;
+        }
+        
+        // Names, config text and error messages that echo input all end up
+        // in innerHTML, so they go through this first
+        function escape(value) {
+            return String(value)
+                .replace(/&/g, '&amp;')
+                .replace(/</g, '&lt;')
+                .replace(/>/g, '&gt;')
+                .replace(/"/g, '&quot;')
+                .replace(/'/g, '&#39;');
+        }
+        
         function showError(message) {
             document.getElementById('results').innerHTML = `
                 <div class="error">
-                    <strong>Error:</strong> ${message}
+                    <strong>Error:</strong> ${escape(message)}
                 </div>
             `;
         }
@@ -360,7 +751,24 @@ async fn serve_index() -> Html<String> {
 </html>
     "#;
     
-    Html(html.to_string())
+    Html(
+        html.replace("{{MIN_LEVEL}}", &MIN_LEVEL.to_string())
+            .replace("{{MAX_LEVEL}}", &MAX_LEVEL.to_string())
+            .replace("{{MAX_WEB_COUNT}}", &MAX_WEB_COUNT.to_string())
+            .replace("{{MAX_FUNNEL_PLAYERS}}", &MAX_FUNNEL_PLAYERS.to_string())
+            .replace("{{DEFAULT_METHOD}}", DEFAULT_METHOD),
+    )
+}
+
+fn error_response(seed: Option<u64>, message: String) -> Json<GenerateResponse> {
+    Json(GenerateResponse {
+        characters: vec![],
+        seed,
+        party_ability_total: None,
+        players: None,
+        success: false,
+        message,
+    })
 }
 
 async fn generate_characters(
@@ -368,31 +776,117 @@ async fn generate_characters(
     Json(request): Json<GenerateRequest>,
 ) -> Result<Json<GenerateResponse>, StatusCode> {
     // Validate input
-    if request.level < 1 || request.level > 20 {
-        return Ok(Json(GenerateResponse {
-            characters: vec![],
-            success: false,
-            message: "Level must be between 1 and 20".to_string(),
-        }));
+    let levels: LevelSpec = match request.level.parse() {
+        Ok(levels) => levels,
+        Err(e) => return Ok(error_response(request.seed, format!("Invalid level: {}", e))),
+    };
+    
+    if request.count < 1 || request.count > MAX_WEB_COUNT {
+        return Ok(error_response(
+            request.seed,
+            format!("Number of characters must be between 1 and {}", MAX_WEB_COUNT),
+        ));
     }
     
-    if request.count < 1 || request.count > 20 {
-        return Ok(Json(GenerateResponse {
-            characters: vec![],
-            success: false,
-            message: "Number of characters must be between 1 and 20".to_string(),
-        }));
+    if request.seed.is_some_and(|seed| seed > MAX_SEED) {
+        return Ok(error_response(request.seed, format!("Seed must be between 0 and {}", MAX_SEED)));
     }
     
+    let method: RollSpec = match request.method.as_deref().unwrap_or(DEFAULT_METHOD).parse() {
+        Ok(method) => method,
+        Err(e) => return Ok(error_response(request.seed, e.to_string())),
+    };
+    
+    let generator = match request.ability_method.as_deref().map(str::parse::<AbilityMethod>) {
+        None => generator,
+        Some(Ok(method)) => match (*generator).clone().with_ability_method(method) {
+            Ok(generator) => Arc::new(generator),
+            Err(e) => return Ok(error_response(request.seed, e.to_string())),
+        },
+        Some(Err(e)) => return Ok(error_response(request.seed, e.to_string())),
+    };
+    
     // Generate characters using shared logic
-    match generator.generate_characters(request.level, request.count) {
-        Ok(characters) => Ok(Json(GenerateResponse {
+    let seed = request.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+    let count = request.count as u8;
+    if let Some(players) = request.funnel_players {
+        if request.party.is_some() {
+            return Ok(error_response(request.seed, "A funnel cannot also be generated as a party".to_string()));
+        }
+        if u32::from(players) * request.count > MAX_WEB_COUNT {
+            return Ok(error_response(
+                request.seed,
+                format!("A funnel can have at most {} characters in total", MAX_WEB_COUNT),
+            ));
+        }
+        return match generator.generate_funnel(&mut rng, players, count, &method, &request.constraints) {
+            Ok(players) => Ok(Json(GenerateResponse {
+                characters: players.iter().flat_map(|player| player.characters.clone()).collect(),
+                players: Some(
+                    players
+                        .iter()
+                        .flat_map(|player| std::iter::repeat_n(player.player, player.characters.len()))
+                        .collect(),
+                ),
+                seed: Some(seed),
+                party_ability_total: None,
+                success: true,
+                message: "Funnel generated successfully".to_string(),
+            })),
+            Err(e) => Ok(error_response(Some(seed), e.to_string())),
+        };
+    }
+    let generated = match &request.party {
+        Some(rules) => generator
+            .generate_party(&mut rng, &levels, count, &method, &request.constraints, rules)
+            .map(|party| (Some(party.ability_total()), party.members)),
+        None => generator
+            .generate_characters(&mut rng, &levels, count, &method, &request.constraints)
+            .map(|characters| (None, characters)),
+    };
+    match generated {
+        Ok((party_ability_total, characters)) => Ok(Json(GenerateResponse {
             characters,
+            seed: Some(seed),
+            party_ability_total,
+            players: None,
             success: true,
             message: "Characters generated successfully".to_string(),
         })),
-        Err(e) => Ok(Json(GenerateResponse {
-            characters: vec![],
+        Err(e) => Ok(error_response(Some(seed), e.to_string())),
+    }
+}
+
+async fn level_up_character(
+    State(generator): State<AppState>,
+    Json(request): Json<LevelUpRequest>,
+) -> Result<Json<LevelUpResponse>, StatusCode> {
+    if request.seed.is_some_and(|seed| seed > MAX_SEED) {
+        return Ok(Json(LevelUpResponse {
+            character: None,
+            gained: None,
+            seed: request.seed,
+            success: false,
+            message: format!("Seed must be between 0 and {}", MAX_SEED),
+        }));
+    }
+    
+    let seed = request.seed.unwrap_or_else(random_seed);
+    let mut rng = seeded_rng(seed);
+    let mut character = request.character;
+    match generator.level_up(&mut rng, &mut character) {
+        Ok(gained) => Ok(Json(LevelUpResponse {
+            message: format!("Character reached level {}", gained.level),
+            character: Some(character),
+            gained: Some(gained),
+            seed: Some(seed),
+            success: true,
+        })),
+        Err(e) => Ok(Json(LevelUpResponse {
+            character: None,
+            gained: None,
+            seed: Some(seed),
             success: false,
             message: e.to_string(),
         })),
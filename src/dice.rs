@@ -0,0 +1,586 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// Dice notation such as "4d6kh3", "3d6+1", "3d6r1" or "4d6dl1", parsed into a
+// list of signed terms that can be rolled against any RNG.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceExpr {
+    pub terms: Vec<(Sign, Term)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sign {
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Dice(DiceRoll),
+    Constant(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceRoll {
+    pub count: u32,
+    pub faces: u32,
+    // dice showing this value or lower are rolled again
+    pub reroll: Option<u32>,
+    pub selection: Option<Selection>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    KeepHighest(u32),
+    KeepLowest(u32),
+    DropHighest(u32),
+    DropLowest(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiceParseError {
+    Empty,
+    UnexpectedChar { found: char, position: usize },
+    ExpectedNumber { position: usize },
+    NumberTooLarge { position: usize },
+    DuplicateModifier { position: usize },
+}
+
+impl fmt::Display for DiceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiceParseError::Empty => write!(f, "dice expression is empty"),
+            DiceParseError::UnexpectedChar { found, position } => {
+                write!(f, "unexpected '{}' at position {}", found, position)
+            }
+            DiceParseError::ExpectedNumber { position } => {
+                write!(f, "expected a number at position {}", position)
+            }
+            DiceParseError::NumberTooLarge { position } => {
+                write!(f, "number at position {} is too large", position)
+            }
+            DiceParseError::DuplicateModifier { position } => {
+                write!(f, "modifier at position {} is already set for this roll", position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiceParseError {}
+
+// Most dice a single term may roll, so a typo like "4000000d6kh3" is rejected
+// instead of allocating millions of rolls
+pub const MAX_DICE: u32 = 100;
+
+// A dice expression that has been checked to be safe to roll for an ability
+// score: every die has faces, rerolls and selections are satisfiable, and the
+// total always fits in a u8.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RollSpec {
+    expr: DiceExpr,
+    min: i64,
+    max: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RollSpecError {
+    Parse(DiceParseError),
+    ZeroFaces,
+    TooManyDice { count: u32 },
+    SelectionExceedsDice { selected: u32, count: u32 },
+    RerollsEveryFace { threshold: u32, faces: u32 },
+    OutOfRange { min: i64, max: i64 },
+}
+
+impl fmt::Display for RollSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RollSpecError::Parse(e) => write!(f, "invalid dice expression: {}", e),
+            RollSpecError::ZeroFaces => write!(f, "dice must have at least one face"),
+            RollSpecError::TooManyDice { count } => {
+                write!(f, "cannot roll {} dice at once (maximum is {})", count, MAX_DICE)
+            }
+            RollSpecError::SelectionExceedsDice { selected, count } => {
+                write!(f, "cannot keep or drop {} of only {} dice", selected, count)
+            }
+            RollSpecError::RerollsEveryFace { threshold, faces } => {
+                write!(f, "rerolling {} or lower on a d{} would never stop", threshold, faces)
+            }
+            RollSpecError::OutOfRange { min, max } => {
+                write!(f, "rolls range from {} to {}, but scores must be between 0 and {}", min, max, u8::MAX)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RollSpecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RollSpecError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DiceParseError> for RollSpecError {
+    fn from(e: DiceParseError) -> Self {
+        RollSpecError::Parse(e)
+    }
+}
+
+impl RollSpec {
+    // Roll `dice` dice with `faces` faces and drop the `lowest` lowest
+    pub fn new(dice: u8, faces: u8, lowest: u8) -> Result<Self, RollSpecError> {
+        let selection = (lowest > 0).then_some(Selection::DropLowest(u32::from(lowest)));
+        let roll = DiceRoll {
+            count: u32::from(dice),
+            faces: u32::from(faces),
+            reroll: None,
+            selection,
+        };
+
+        Self::try_from(DiceExpr { terms: vec![(Sign::Plus, Term::Dice(roll))] })
+    }
+
+    pub fn expr(&self) -> &DiceExpr {
+        &self.expr
+    }
+
+    pub fn min(&self) -> u8 {
+        self.min as u8
+    }
+
+    pub fn max(&self) -> u8 {
+        self.max as u8
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> u8 {
+        // Validation guarantees the total lies within min..=max
+        self.expr.roll(rng) as u8
+    }
+}
+
+impl Default for RollSpec {
+    fn default() -> Self {
+        Self::new(3, 6, 0).expect("3d6 is a valid roll")
+    }
+}
+
+impl TryFrom<DiceExpr> for RollSpec {
+    type Error = RollSpecError;
+
+    fn try_from(expr: DiceExpr) -> Result<Self, Self::Error> {
+        let mut min: i64 = 0;
+        let mut max: i64 = 0;
+
+        for (sign, term) in &expr.terms {
+            let (low, high) = match term {
+                Term::Constant(value) => (i64::from(*value), i64::from(*value)),
+                Term::Dice(dice) => dice.bounds()?,
+            };
+            match sign {
+                Sign::Plus => {
+                    min = min.saturating_add(low);
+                    max = max.saturating_add(high);
+                }
+                Sign::Minus => {
+                    min = min.saturating_sub(high);
+                    max = max.saturating_sub(low);
+                }
+            }
+        }
+
+        if min < 0 || max > i64::from(u8::MAX) {
+            return Err(RollSpecError::OutOfRange { min, max });
+        }
+
+        Ok(Self { expr, min, max })
+    }
+}
+
+impl TryFrom<String> for RollSpec {
+    type Error = RollSpecError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<RollSpec> for String {
+    fn from(spec: RollSpec) -> Self {
+        spec.to_string()
+    }
+}
+
+impl FromStr for RollSpec {
+    type Err = RollSpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.parse::<DiceExpr>()?)
+    }
+}
+
+impl fmt::Display for RollSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+impl DiceExpr {
+    // Only safe once `RollSpec` has checked the expression: "1d0" panics and
+    // "3d6r6" never stops rerolling
+    pub(crate) fn roll(&self, rng: &mut impl Rng) -> i64 {
+        self.terms
+            .iter()
+            .map(|(sign, term)| {
+                let value = match term {
+                    Term::Dice(dice) => dice.roll(rng),
+                    Term::Constant(value) => i64::from(*value),
+                };
+                match sign {
+                    Sign::Plus => value,
+                    Sign::Minus => -value,
+                }
+            })
+            .sum()
+    }
+}
+
+impl DiceRoll {
+    // Lowest and highest possible totals, or why this roll can't be made
+    fn bounds(&self) -> Result<(i64, i64), RollSpecError> {
+        if self.faces == 0 {
+            return Err(RollSpecError::ZeroFaces);
+        }
+
+        if self.count > MAX_DICE {
+            return Err(RollSpecError::TooManyDice { count: self.count });
+        }
+
+        let lowest_face = match self.reroll {
+            Some(threshold) if threshold >= self.faces => {
+                return Err(RollSpecError::RerollsEveryFace { threshold, faces: self.faces })
+            }
+            Some(threshold) => threshold + 1,
+            None => 1,
+        };
+
+        let kept = match self.selection {
+            None => self.count,
+            Some(Selection::KeepHighest(n) | Selection::KeepLowest(n) | Selection::DropHighest(n) | Selection::DropLowest(n))
+                if n > self.count =>
+            {
+                return Err(RollSpecError::SelectionExceedsDice { selected: n, count: self.count })
+            }
+            Some(Selection::KeepHighest(n) | Selection::KeepLowest(n)) => n,
+            Some(Selection::DropHighest(n) | Selection::DropLowest(n)) => self.count - n,
+        };
+
+        Ok((i64::from(kept) * i64::from(lowest_face), i64::from(kept) * i64::from(self.faces)))
+    }
+
+    pub(crate) fn roll(&self, rng: &mut impl Rng) -> i64 {
+        let mut rolls: Vec<u32> = (0..self.count)
+            .map(|_| {
+                let mut roll = rng.gen_range(1..=self.faces);
+                if let Some(threshold) = self.reroll {
+                    while roll <= threshold {
+                        roll = rng.gen_range(1..=self.faces);
+                    }
+                }
+                roll
+            })
+            .collect();
+        rolls.sort_unstable();
+
+        let count = rolls.len();
+        let kept = match self.selection {
+            None => &rolls[..],
+            Some(Selection::KeepHighest(n)) => &rolls[count.saturating_sub(n as usize)..],
+            Some(Selection::KeepLowest(n)) => &rolls[..count.min(n as usize)],
+            Some(Selection::DropHighest(n)) => &rolls[..count.saturating_sub(n as usize)],
+            Some(Selection::DropLowest(n)) => &rolls[count.min(n as usize)..],
+        };
+
+        kept.iter().map(|&roll| i64::from(roll)).sum()
+    }
+}
+
+impl FromStr for DiceExpr {
+    type Err = DiceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s).parse()
+    }
+}
+
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (sign, term)) in self.terms.iter().enumerate() {
+            match (i, sign) {
+                (0, Sign::Plus) => {}
+                (_, Sign::Plus) => write!(f, "+")?,
+                (_, Sign::Minus) => write!(f, "-")?,
+            }
+            match term {
+                Term::Dice(dice) => write!(f, "{}", dice)?,
+                Term::Constant(value) => write!(f, "{}", value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DiceRoll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.faces)?;
+        if let Some(threshold) = self.reroll {
+            write!(f, "r{}", threshold)?;
+        }
+        match self.selection {
+            None => Ok(()),
+            Some(Selection::KeepHighest(n)) => write!(f, "kh{}", n),
+            Some(Selection::KeepLowest(n)) => write!(f, "kl{}", n),
+            Some(Selection::DropHighest(n)) => write!(f, "dh{}", n),
+            Some(Selection::DropLowest(n)) => write!(f, "dl{}", n),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        // Whitespace and case carry no meaning in dice notation
+        let chars = input
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        Self { chars, position: 0 }
+    }
+
+    fn parse(mut self) -> Result<DiceExpr, DiceParseError> {
+        if self.chars.is_empty() {
+            return Err(DiceParseError::Empty);
+        }
+
+        let mut terms = Vec::new();
+        let mut sign = match self.peek() {
+            Some('-') => {
+                self.position += 1;
+                Sign::Minus
+            }
+            Some('+') => {
+                self.position += 1;
+                Sign::Plus
+            }
+            _ => Sign::Plus,
+        };
+
+        loop {
+            terms.push((sign, self.parse_term()?));
+
+            sign = match self.peek() {
+                None => break,
+                Some('+') => Sign::Plus,
+                Some('-') => Sign::Minus,
+                Some(found) => {
+                    return Err(DiceParseError::UnexpectedChar { found, position: self.position })
+                }
+            };
+            self.position += 1;
+        }
+
+        Ok(DiceExpr { terms })
+    }
+
+    fn parse_term(&mut self) -> Result<Term, DiceParseError> {
+        // "d20" is shorthand for "1d20"
+        let count = match self.peek() {
+            Some('d') => 1,
+            _ => self.parse_number()?,
+        };
+
+        if self.peek() != Some('d') {
+            // A bare number is a flat modifier
+            return Ok(Term::Constant(count));
+        }
+        self.position += 1;
+
+        let faces = self.parse_number()?;
+        let mut dice = DiceRoll {
+            count,
+            faces,
+            reroll: None,
+            selection: None,
+        };
+
+        loop {
+            let start = self.position;
+            let tag = match (self.peek(), self.peek_at(1)) {
+                (Some('r'), _) => "r",
+                (Some('k'), Some('h')) => "kh",
+                (Some('k'), Some('l')) => "kl",
+                (Some('d'), Some('h')) => "dh",
+                (Some('d'), Some('l')) => "dl",
+                _ => break,
+            };
+            self.position += tag.len();
+            let value = self.parse_number()?;
+
+            let duplicate = match tag {
+                "r" => dice.reroll.replace(value).is_some(),
+                "kh" => dice.selection.replace(Selection::KeepHighest(value)).is_some(),
+                "kl" => dice.selection.replace(Selection::KeepLowest(value)).is_some(),
+                "dh" => dice.selection.replace(Selection::DropHighest(value)).is_some(),
+                _ => dice.selection.replace(Selection::DropLowest(value)).is_some(),
+            };
+            if duplicate {
+                return Err(DiceParseError::DuplicateModifier { position: start });
+            }
+        }
+
+        Ok(Term::Dice(dice))
+    }
+
+    fn parse_number(&mut self) -> Result<u32, DiceParseError> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        if start == self.position {
+            return match self.peek() {
+                Some(found) => Err(DiceParseError::UnexpectedChar { found, position: start }),
+                None => Err(DiceParseError::ExpectedNumber { position: start }),
+            };
+        }
+
+        self.chars[start..self.position]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map_err(|_| DiceParseError::NumberTooLarge { position: start })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn dice(count: u32, faces: u32, reroll: Option<u32>, selection: Option<Selection>) -> Term {
+        Term::Dice(DiceRoll { count, faces, reroll, selection })
+    }
+
+    #[test]
+    fn test_parse_notation() {
+        assert_eq!(
+            "4d6kh3".parse::<DiceExpr>().unwrap().terms,
+            vec![(Sign::Plus, dice(4, 6, None, Some(Selection::KeepHighest(3))))]
+        );
+        assert_eq!(
+            "3d6+1".parse::<DiceExpr>().unwrap().terms,
+            vec![(Sign::Plus, dice(3, 6, None, None)), (Sign::Plus, Term::Constant(1))]
+        );
+        assert_eq!(
+            "3d6r1".parse::<DiceExpr>().unwrap().terms,
+            vec![(Sign::Plus, dice(3, 6, Some(1), None))]
+        );
+        assert_eq!(
+            "4d6dl1".parse::<DiceExpr>().unwrap().terms,
+            vec![(Sign::Plus, dice(4, 6, None, Some(Selection::DropLowest(1))))]
+        );
+        assert_eq!(
+            "d20".parse::<DiceExpr>().unwrap().terms,
+            vec![(Sign::Plus, dice(1, 20, None, None))]
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for notation in ["4d6kh3", "3d6+1", "2d6+6", "1d20", "3d6r1", "4d6dl1", "2d8-1"] {
+            assert_eq!(notation.parse::<DiceExpr>().unwrap().to_string(), notation);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("".parse::<DiceExpr>(), Err(DiceParseError::Empty));
+        assert_eq!("3d".parse::<DiceExpr>(), Err(DiceParseError::ExpectedNumber { position: 2 }));
+        assert_eq!(
+            "3x6".parse::<DiceExpr>(),
+            Err(DiceParseError::UnexpectedChar { found: 'x', position: 1 })
+        );
+        assert_eq!(
+            "4d6kh3dl1".parse::<DiceExpr>(),
+            Err(DiceParseError::DuplicateModifier { position: 6 })
+        );
+    }
+
+    #[test]
+    fn test_roll_spec_validation() {
+        assert_eq!(RollSpec::new(3, 0, 0), Err(RollSpecError::ZeroFaces));
+        assert_eq!(
+            RollSpec::new(3, 6, 4),
+            Err(RollSpecError::SelectionExceedsDice { selected: 4, count: 3 })
+        );
+        assert_eq!(
+            "20d20".parse::<RollSpec>(),
+            Err(RollSpecError::OutOfRange { min: 20, max: 400 })
+        );
+        assert_eq!(
+            "1d6-2".parse::<RollSpec>(),
+            Err(RollSpecError::OutOfRange { min: -1, max: 4 })
+        );
+        assert_eq!(
+            "3d6r6".parse::<RollSpec>(),
+            Err(RollSpecError::RerollsEveryFace { threshold: 6, faces: 6 })
+        );
+        assert_eq!("101d1kh3".parse::<RollSpec>(), Err(RollSpecError::TooManyDice { count: 101 }));
+        assert!(matches!("3e6".parse::<RollSpec>(), Err(RollSpecError::Parse(_))));
+    }
+
+    #[test]
+    fn test_roll_spec_bounds() {
+        let spec: RollSpec = "4d6kh3".parse().unwrap();
+        assert_eq!((spec.min(), spec.max()), (3, 18));
+
+        let spec: RollSpec = "3d6r1+1".parse().unwrap();
+        assert_eq!((spec.min(), spec.max()), (7, 19));
+
+        let spec = RollSpec::new(4, 6, 1).unwrap();
+        assert_eq!(spec.to_string(), "4d6dl1");
+    }
+
+    #[test]
+    fn test_roll_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let expr: DiceExpr = "4d6kh3".parse().unwrap();
+        let reroll: DiceExpr = "3d6r1".parse().unwrap();
+        let flat: DiceExpr = "2d6+6".parse().unwrap();
+
+        for _ in 0..1000 {
+            assert!((3..=18).contains(&expr.roll(&mut rng)));
+            assert!((6..=18).contains(&reroll.roll(&mut rng)));
+            assert!((8..=18).contains(&flat.roll(&mut rng)));
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::dice::RollSpec;
+use crate::inventory::Inventory;
+use crate::{
+    ability_modifier, choose_allowed, name_or_record, write_characters_file, Character, CharacterFile, CharacterGenerator, Constraints,
+    DerivedStats, Item,
+};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Funnel characters start below the first level, with no class
+pub const FUNNEL_LEVEL: u8 = 0;
+
+// Funnels are rolled at the table, so keep them to a sensible size
+pub const MAX_FUNNEL_PLAYERS: u8 = 12;
+pub const MAX_FUNNEL_PER_PLAYER: u8 = 8;
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FunnelRules {
+    // Rolled for HP, plus the Constitution bonus; never less than 1
+    pub hit_points: RollSpec,
+    pub occupations: Vec<Occupation>,
+}
+
+impl Default for FunnelRules {
+    fn default() -> Self {
+        Self {
+            hit_points: RollSpec::new(1, 4, 0).expect("1d4 is a valid roll"),
+            occupations: Vec::new(),
+        }
+    }
+}
+
+// What a funnel character did before adventuring, and the one item they
+// bring along
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Occupation {
+    pub name: String,
+    #[serde(deserialize_with = "name_or_record")]
+    pub item: Item,
+}
+
+// One player's stable of funnel characters
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FunnelPlayer {
+    pub player: u8,
+    pub characters: Vec<Character>,
+}
+
+impl CharacterGenerator {
+    pub fn generate_funnel(
+        &self,
+        rng: &mut impl Rng,
+        players: u8,
+        per_player: u8,
+        method: &RollSpec,
+        constraints: &Constraints,
+    ) -> anyhow::Result<Vec<FunnelPlayer>> {
+        if !(1..=MAX_FUNNEL_PLAYERS).contains(&players) {
+            return Err(anyhow::anyhow!("A funnel needs between 1 and {} players", MAX_FUNNEL_PLAYERS));
+        }
+        if !(1..=MAX_FUNNEL_PER_PLAYER).contains(&per_player) {
+            return Err(anyhow::anyhow!("Each player needs between 1 and {} funnel characters", MAX_FUNNEL_PER_PLAYER));
+        }
+
+        (1..=players)
+            .map(|player| {
+                let characters = (0..per_player)
+                    .map(|_| self.generate_funnel_character(rng, method, constraints))
+                    .collect::<anyhow::Result<_>>()?;
+                Ok(FunnelPlayer { player, characters })
+            })
+            .collect()
+    }
+
+    // A classless level 0 character with an occupation and its one item
+    pub fn generate_funnel_character(&self, rng: &mut impl Rng, method: &RollSpec, constraints: &Constraints) -> anyhow::Result<Character> {
+        if !constraints.classes.is_empty()
+            || !constraints.exclude_classes.is_empty()
+            || !constraints.subclasses.is_empty()
+            || !constraints.exclude_subclasses.is_empty()
+        {
+            return Err(anyhow::anyhow!("Level {} characters have no class to limit", FUNNEL_LEVEL));
+        }
+        if self.config.funnel.occupations.is_empty() {
+            return Err(anyhow::anyhow!("Config file has no funnel occupations"));
+        }
+        self.validate_constraints(constraints)?;
+
+        let setting = self.setting.as_deref();
+        let species = choose_allowed(
+            rng,
+            "species",
+            &self.config.species,
+            |species| constraints.allows_species(species),
+            |species| self.config.species_weight(setting, species),
+        )?;
+        let name = self.config.names_for(species).generate(rng);
+        let quirk = self.config.quirks_for(species).choose(rng).cloned();
+        let occupation = self
+            .config
+            .funnel
+            .occupations
+            .choose(rng)
+            .expect("occupations are checked above");
+
+        let mut ability_scores = self.generate_ability_scores(rng, method, &[])?;
+        ability_scores.adjust(&species.ability_adjustments);
+        let hit_points = i32::from(self.config.funnel.hit_points.roll(rng)) + i32::from(ability_modifier(ability_scores.constitution));
+        let mut derived_stats = DerivedStats::from_ability_scores(&ability_scores);
+        let inventory = Inventory::with_items(&ability_scores, [occupation.item.clone()]);
+        derived_stats.apply_encumbrance(&inventory);
+        derived_stats.apply_armor(&inventory);
+
+        Ok(Character {
+            name,
+            level: FUNNEL_LEVEL,
+            classes: Vec::new(),
+            species: species.name.clone(),
+            traits: species.traits.clone(),
+            quirk,
+            background: None,
+            occupation: Some(occupation.name.clone()),
+            hit_points: hit_points.clamp(1, i32::from(u16::MAX)) as u16,
+            ability_scores,
+            derived_stats,
+            features: Vec::new(),
+            magic: None,
+            inventory,
+        })
+    }
+}
+
+// Saved as a characters file, each character tagged with their player, so
+// it loads and levels up like any other. Named after the number of players
+// and characters, e.g. funnel_players_4_count_12.toml
+pub fn save_funnel_to_file(players: &[FunnelPlayer], seed: u64) -> anyhow::Result<String> {
+    let file = CharacterFile {
+        seed,
+        level_ups: Vec::new(),
+        players: players
+            .iter()
+            .flat_map(|player| std::iter::repeat_n(player.player, player.characters.len()))
+            .collect(),
+        characters: players.iter().flat_map(|player| player.characters.clone()).collect(),
+    };
+    let filename = format!("funnel_players_{}_count_{}.toml", players.len(), file.characters.len());
+    write_characters_file(&filename, &file)?;
+
+    Ok(filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{seeded_rng, Config};
+
+    fn generator() -> CharacterGenerator {
+        let config: Config = toml::from_str(
+            r#"
+            species = ["Human", "Goblin"]
+            classes = ["Fighter"]
+
+            [funnel]
+            hit_points = "1d2"
+            occupations = [
+                { name = "Farmer", item = { name = "Pitchfork", damage = "d6", slots = 2 } },
+                { name = "Beggar", item = "Tin cup" },
+            ]
+            "#,
+        )
+        .unwrap();
+        CharacterGenerator::from_config(config)
+    }
+
+    #[test]
+    fn test_funnel_groups_characters_by_player() {
+        let generator = generator();
+        let players = generator
+            .generate_funnel(&mut seeded_rng(3), 4, 3, &RollSpec::default(), &Constraints::default())
+            .unwrap();
+
+        assert_eq!(players.iter().map(|player| player.player).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        for character in players.iter().flat_map(|player| &player.characters) {
+            assert_eq!(character.level, FUNNEL_LEVEL);
+            assert!(character.classes.is_empty() && character.features.is_empty() && character.magic.is_none());
+            assert!(character.background.is_none());
+
+            // One item, from the occupation
+            let items: Vec<&str> = character.inventory.items().map(|item| item.name.as_str()).collect();
+            match character.occupation.as_deref() {
+                Some("Farmer") => assert_eq!(items, ["Pitchfork"]),
+                Some("Beggar") => assert_eq!(items, ["Tin cup"]),
+                other => panic!("unexpected occupation {:?}", other),
+            }
+
+            // 1d2 plus at most +3 for Constitution
+            assert!((1..=5).contains(&character.hit_points));
+        }
+        assert_eq!(players.iter().map(|player| player.characters.len()).sum::<usize>(), 12);
+    }
+
+    #[test]
+    fn test_funnel_limits() {
+        let generator = generator();
+        let funnel = |players, per_player, constraints: &Constraints| {
+            generator.generate_funnel(&mut seeded_rng(0), players, per_player, &RollSpec::default(), constraints)
+        };
+
+        assert!(funnel(0, 3, &Constraints::default()).is_err());
+        assert!(funnel(2, 0, &Constraints::default()).is_err());
+        assert!(funnel(MAX_FUNNEL_PLAYERS + 1, 1, &Constraints::default()).is_err());
+
+        let goblins = Constraints {
+            species: vec!["Goblin".to_string()],
+            ..Constraints::default()
+        };
+        let players = funnel(1, 4, &goblins).unwrap();
+        assert!(players[0].characters.iter().all(|character| character.species == "Goblin"));
+
+        let fighters = Constraints {
+            classes: vec!["Fighter".to_string()],
+            ..Constraints::default()
+        };
+        let err = funnel(1, 1, &fighters).unwrap_err();
+        assert_eq!(err.to_string(), "Level 0 characters have no class to limit");
+
+        let mut config = generator.get_config().clone();
+        config.funnel.occupations.clear();
+        let err = CharacterGenerator::from_config(config)
+            .generate_funnel(&mut seeded_rng(0), 1, 1, &RollSpec::default(), &Constraints::default())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Config file has no funnel occupations");
+    }
+}
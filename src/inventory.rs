@@ -0,0 +1,146 @@
+use crate::{AbilityScores, Item};
+use serde::{Deserialize, Serialize};
+
+// Belt slots for small items that can be drawn without an action. They sit
+// outside the pack and don't count against its capacity.
+pub const QUICK_DRAW_SLOTS: usize = 3;
+
+// Slots a character can carry, split between the belt and the pack. The pack
+// holds one slot per point of Strength; every slot past that is a point of
+// encumbrance, costing 1 Stealth and 1 Movement.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Inventory {
+    pub capacity: u8,
+    pub quick_draw: Vec<Item>,
+    pub pack: Vec<Item>,
+}
+
+impl Inventory {
+    pub fn new(scores: &AbilityScores) -> Self {
+        Self {
+            capacity: scores.strength,
+            quick_draw: Vec::new(),
+            pack: Vec::new(),
+        }
+    }
+
+    // Place every item, even past capacity; check `is_over_capacity` afterwards
+    pub fn with_items(scores: &AbilityScores, items: impl IntoIterator<Item = Item>) -> Self {
+        let mut inventory = Self::new(scores);
+        for item in items {
+            inventory.place(item);
+        }
+
+        inventory
+    }
+
+    // Belt items go in a free quick-draw slot if there is one, everything
+    // else into the pack
+    pub fn place(&mut self, item: Item) {
+        if item.quick_draw && item.slots <= 1 && self.quick_draw.len() < QUICK_DRAW_SLOTS {
+            self.quick_draw.push(item);
+        } else {
+            self.pack.push(item);
+        }
+    }
+
+    // Like `place`, but refuses an item that would leave the character encumbered
+    pub fn try_place(&mut self, item: Item) -> anyhow::Result<()> {
+        let fits_belt = item.quick_draw && item.slots <= 1 && self.quick_draw.len() < QUICK_DRAW_SLOTS;
+        if !fits_belt && self.used_slots() + u16::from(item.slots) > u16::from(self.capacity) {
+            return Err(anyhow::anyhow!(
+                "No room for {}: {} of {} slots already used",
+                item.name,
+                self.used_slots(),
+                self.capacity
+            ));
+        }
+
+        self.place(item);
+        Ok(())
+    }
+
+    pub fn used_slots(&self) -> u16 {
+        self.pack.iter().map(|item| u16::from(item.slots)).sum()
+    }
+
+    pub fn encumbrance(&self) -> u16 {
+        self.used_slots().saturating_sub(u16::from(self.capacity))
+    }
+
+    pub fn armor(&self) -> u16 {
+        self.items().map(|item| u16::from(item.armor)).sum()
+    }
+
+    pub fn is_over_capacity(&self) -> bool {
+        self.encumbrance() > 0
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.quick_draw.iter().chain(&self.pack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(strength: u8) -> AbilityScores {
+        AbilityScores {
+            strength,
+            dexterity: 10,
+            constitution: 10,
+            intelligence: 10,
+            wisdom: 10,
+            charisma: 10,
+        }
+    }
+
+    fn item(name: &str, slots: u8, quick_draw: bool) -> Item {
+        Item {
+            slots,
+            quick_draw,
+            ..Item::from(name.to_string())
+        }
+    }
+
+    #[test]
+    fn test_quick_draw_slots() {
+        let mut inventory = Inventory::new(&scores(8));
+        for name in ["Dagger", "Torch", "Sling", "Chalk"] {
+            inventory.place(item(name, 1, true));
+        }
+        inventory.place(item("Spear", 2, true));
+
+        let belt: Vec<&str> = inventory.quick_draw.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(belt, vec!["Dagger", "Torch", "Sling"]);
+        assert_eq!(inventory.used_slots(), 3);
+        assert_eq!(inventory.items().count(), 5);
+    }
+
+    #[test]
+    fn test_encumbrance() {
+        let mut inventory = Inventory::new(&scores(4));
+        inventory.place(item("Chain armor", 2, false));
+        inventory.place(item("Rope", 1, false));
+        inventory.place(item("Prayer beads", 0, false));
+        assert_eq!(inventory.encumbrance(), 0);
+
+        inventory.place(item("Greataxe", 2, false));
+        assert_eq!(inventory.used_slots(), 5);
+        assert_eq!(inventory.encumbrance(), 1);
+        assert!(inventory.is_over_capacity());
+    }
+
+    #[test]
+    fn test_try_place_refuses_over_capacity() {
+        let mut inventory = Inventory::new(&scores(3));
+        inventory.try_place(item("Longsword", 2, false)).unwrap();
+        inventory.try_place(item("Dagger", 1, true)).unwrap();
+
+        let err = inventory.try_place(item("Shield", 2, false)).unwrap_err();
+        assert_eq!(err.to_string(), "No room for Shield: 2 of 3 slots already used");
+        inventory.try_place(item("Rope", 1, false)).unwrap();
+        assert!(!inventory.is_over_capacity());
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    choose_allowed, magic_dice_for_templates, seeded_rng, spells_known_for_templates, templates_at_level, Character, CharacterFile,
+    CharacterGenerator, ClassFeature, Constraints, LevelUpRecord, Magic, Spell, MAX_LEVEL, MIN_LEVEL,
+};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+
+// What a character gained by going up a level
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LevelUp {
+    pub level: u8,
+    pub hit_points: u16,
+    pub features: Vec<ClassFeature>,
+    pub magic_dice: u8,
+    pub spells: Vec<Spell>,
+}
+
+impl CharacterGenerator {
+    // Advance a character by one level: roll more HP, then take the next
+    // template (possibly in a new class, by the multiclass policy, or a first
+    // class for a level 0 character) and, for casters, its Magic Dice and
+    // spells. The character is left untouched if
+    // it can't go up a level.
+    pub fn level_up(&self, rng: &mut impl Rng, character: &mut Character) -> anyhow::Result<LevelUp> {
+        if character.level >= MAX_LEVEL {
+            return Err(anyhow::anyhow!("Character is already at the highest level, {}", MAX_LEVEL));
+        }
+        for class in &character.classes {
+            let Some(record) = self.config.class(&class.name) else {
+                return Err(anyhow::anyhow!("Unknown class: {}", class.name));
+            };
+            if let Some(subclass) = &class.subclass {
+                if !record.subclasses.iter().any(|record| &record.name == subclass) {
+                    return Err(anyhow::anyhow!("Unknown {} subclass: {}", class.name, subclass));
+                }
+            }
+        }
+
+        let level = character.level + 1;
+        let mut classes = character.classes.clone();
+        let rules = &self.config.hit_points;
+        // Reaching the first level gives the base HP, every level after that a roll
+        let mut hit_points = if level == MIN_LEVEL {
+            u16::from(rules.base)
+        } else {
+            u16::from(rules.per_level.roll(rng))
+        };
+        let mut features = Vec::new();
+        if templates_at_level(level) > templates_at_level(character.level) {
+            // A funnel survivor takes up their first class
+            let index = if classes.is_empty() {
+                let class = choose_allowed(
+                    rng,
+                    "class",
+                    &self.config.classes,
+                    |_| true,
+                    |class| self.config.class_weight(self.setting.as_deref(), class),
+                )?;
+                classes.push(self.start_class(rng, class, &Constraints::default())?);
+                0
+            } else {
+                self.take_template(rng, &mut classes, &Constraints::default())?
+            };
+            let class = &classes[index];
+            hit_points += u16::from(self.template_hit_points(&class.name));
+            features = self.template_features(&class.name, usize::from(class.templates - 1));
+        }
+
+        // A character who has just branched into a casting class starts
+        // casting here
+        let mut magic = character.magic.clone();
+        let mut magic_dice = 0;
+        let mut spells = Vec::new();
+        if let Some((class, subclass)) = self.casting_class(&classes) {
+            let magic = magic.get_or_insert_with(|| Magic {
+                magic_dice: 0,
+                spells: Vec::new(),
+                cantrips: subclass.cantrips.clone(),
+                perks: subclass.perks.clone(),
+                drawbacks: subclass.drawbacks.clone(),
+            });
+            magic_dice = magic_dice_for_templates(class.templates).saturating_sub(magic.magic_dice);
+            let unknown: Vec<&Spell> = subclass
+                .spells
+                .iter()
+                .filter(|spell| !magic.spells.iter().any(|known| known.name == spell.name))
+                .collect();
+            spells = unknown
+                .choose_multiple(rng, spells_known_for_templates(class.templates).saturating_sub(magic.spells.len()))
+                .map(|&spell| spell.clone())
+                .collect();
+
+            magic.magic_dice += magic_dice;
+            magic.spells.extend(spells.iter().cloned());
+        }
+
+        character.level = level;
+        character.classes = classes;
+        character.hit_points = character.hit_points.saturating_add(hit_points);
+        character.features.extend(features.iter().cloned());
+        character.magic = magic;
+
+        Ok(LevelUp {
+            level,
+            hit_points,
+            features,
+            magic_dice,
+            spells,
+        })
+    }
+
+    // Level up every character in a saved file, or only the `only`th
+    // (counting from 1), and record the seed in the file.
+    // Returns what each levelled character gained, by number. The file is
+    // left untouched unless every character can go up a level.
+    pub fn level_up_file(&self, file: &mut CharacterFile, seed: u64, only: Option<u64>) -> anyhow::Result<Vec<(usize, LevelUp)>> {
+        if only.is_some_and(|number| number < 1 || number > file.characters.len() as u64) {
+            return Err(anyhow::anyhow!("The file only has {} character(s)", file.characters.len()));
+        }
+
+        let mut rng = seeded_rng(seed);
+        let mut characters = file.characters.clone();
+        let mut gains = Vec::new();
+        for (index, character) in characters.iter_mut().enumerate() {
+            let number = index + 1;
+            if only.is_some_and(|only| only != number as u64) {
+                continue;
+            }
+            let gained = self
+                .level_up(&mut rng, character)
+                .map_err(|e| anyhow::anyhow!("Character {}: {}", number, e))?;
+            gains.push((number, gained));
+        }
+
+        file.characters = characters;
+        file.level_ups.push(LevelUpRecord { seed, character: only });
+        Ok(gains)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::RollSpec;
+    use crate::multiclass::MulticlassPolicy;
+    use crate::{CharacterClass, Config};
+
+    fn generator() -> CharacterGenerator {
+        let config: Config = toml::from_str(
+            r#"
+            species = ["Human"]
+            classes = [
+                "Fighter",
+                { name = "Wizard", subclasses = [
+                    { name = "Necromancer", spells = ["Raise Corpse", "Bone Spear", "Speak with Dead", "Death Mask", "Wither"] },
+                ] },
+            ]
+
+            [hit_points]
+            per_level = "1d6"
+            template_bonus = { Fighter = 2 }
+
+            [class_templates.Fighter]
+            A = [{ name = "Parry", text = "Reduce damage" }]
+            B = [{ name = "Cleave", text = "Attack again" }]
+            D = [{ name = "Second Wind", text = "Heal once a day" }]
+            "#,
+        )
+        .unwrap();
+        CharacterGenerator::from_config(config)
+    }
+
+    fn roll(generator: &CharacterGenerator, class: &str, level: u8) -> Character {
+        let constraints = Constraints {
+            classes: vec![class.to_string()],
+            ..Constraints::default()
+        };
+        generator
+            .generate_character(&mut seeded_rng(2), level, &RollSpec::default(), &constraints)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_level_up_gains_templates_and_hit_points() {
+        let generator = generator();
+        let mut character = roll(&generator, "Fighter", 1);
+        let mut rng = seeded_rng(8);
+
+        let mut hit_points = character.hit_points;
+        for level in 2..=4 {
+            let gained = generator.level_up(&mut rng, &mut character).unwrap();
+            assert_eq!((gained.level, character.level), (level, level));
+            // 1d6 plus the Fighter's bonus for a new template
+            assert!((3..=8).contains(&gained.hit_points), "{}", gained.hit_points);
+            assert_eq!(character.hit_points, hit_points + gained.hit_points);
+            hit_points = character.hit_points;
+        }
+
+        let features: Vec<&str> = character.features.iter().map(|feature| feature.name.as_str()).collect();
+        assert_eq!(features, ["Parry", "Cleave", "Second Wind"]);
+        assert_eq!(character.features, generator.class_features("Fighter", 4));
+        assert_eq!(character.templates(), ["Fighter A", "Fighter B", "Fighter C", "Fighter D"]);
+
+        // Past template D only the HP roll is left
+        let gained = generator.level_up(&mut rng, &mut character).unwrap();
+        assert!(gained.features.is_empty());
+        assert!((1..=6).contains(&gained.hit_points));
+    }
+
+    #[test]
+    fn test_level_up_learns_spells() {
+        let generator = generator();
+        let mut character = roll(&generator, "Wizard", 1);
+        let mut rng = seeded_rng(4);
+
+        for level in 2..=5 {
+            let before = character.magic.clone().unwrap();
+            let gained = generator.level_up(&mut rng, &mut character).unwrap();
+            let magic = character.magic.as_ref().unwrap();
+
+            assert_eq!(magic.magic_dice, magic_dice_for_templates(templates_at_level(level)));
+            assert_eq!(magic.spells.len(), spells_known_for_templates(templates_at_level(level)).min(5));
+            assert_eq!(magic.magic_dice, before.magic_dice + gained.magic_dice);
+            assert!(gained.spells.iter().all(|spell| !before.spells.contains(spell)));
+        }
+
+        // Every spell on the list is known by now, so there is nothing left to learn
+        let gained = generator.level_up(&mut rng, &mut character).unwrap();
+        assert!(gained.spells.is_empty());
+        assert_eq!(gained.magic_dice, 0);
+    }
+
+    #[test]
+    fn test_level_up_limits() {
+        let generator = generator();
+        let mut character = roll(&generator, "Fighter", 10);
+        let err = generator.level_up(&mut seeded_rng(0), &mut character).unwrap_err();
+        assert_eq!(err.to_string(), "Character is already at the highest level, 10");
+        assert_eq!(character.level, 10);
+
+        let mut character = roll(&generator, "Wizard", 1);
+        character.classes[0].subclass = Some("Illusionist".to_string());
+        let err = generator.level_up(&mut seeded_rng(0), &mut character).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown Wizard subclass: Illusionist");
+
+        character.classes[0] = CharacterClass {
+            name: "Bard".to_string(),
+            subclass: None,
+            templates: 1,
+        };
+        let err = generator.level_up(&mut seeded_rng(0), &mut character).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown class: Bard");
+        assert_eq!(character.level, 1);
+    }
+
+    #[test]
+    fn test_level_up_file_is_all_or_nothing() {
+        let generator = generator();
+        let mut file = CharacterFile {
+            seed: 2,
+            level_ups: Vec::new(),
+            players: Vec::new(),
+            characters: vec![roll(&generator, "Fighter", 1), roll(&generator, "Wizard", 1)],
+        };
+
+        let gains = generator.level_up_file(&mut file, 7, None).unwrap();
+        assert_eq!(gains.iter().map(|(number, gained)| (*number, gained.level)).collect::<Vec<_>>(), [(1, 2), (2, 2)]);
+        let gains = generator.level_up_file(&mut file, 8, Some(2)).unwrap();
+        assert_eq!(gains.len(), 1);
+        assert_eq!(file.characters.iter().map(|character| character.level).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(
+            file.level_ups,
+            [LevelUpRecord { seed: 7, character: None }, LevelUpRecord { seed: 8, character: Some(2) }]
+        );
+
+        // The first character can't go up, so neither does the second
+        file.characters[0].level = MAX_LEVEL;
+        let before = file.clone();
+        let err = generator.level_up_file(&mut file, 9, None).unwrap_err();
+        assert_eq!(err.to_string(), "Character 1: Character is already at the highest level, 10");
+        assert_eq!(file, before);
+
+        let err = generator.level_up_file(&mut file, 9, Some(3)).unwrap_err();
+        assert_eq!(err.to_string(), "The file only has 2 character(s)");
+    }
+
+    #[test]
+    fn test_level_up_branches_into_casting() {
+        let generator = generator()
+            .with_multiclass(MulticlassPolicy {
+                chance: 1.0,
+                max_classes: 2,
+            })
+            .unwrap();
+        let mut character = roll(&generator, "Fighter", 1);
+        assert!(character.magic.is_none());
+
+        let gained = generator.level_up(&mut seeded_rng(3), &mut character).unwrap();
+        assert_eq!(character.class_name(), "Fighter / Wizard (Necromancer)");
+        assert_eq!(character.templates(), ["Fighter A", "Wizard A"]);
+        assert_eq!(gained.magic_dice, 1);
+        assert_eq!(gained.spells.len(), 2);
+        assert_eq!(character.magic.unwrap().spells, gained.spells);
+    }
+
+    #[test]
+    fn test_funnel_survivor_takes_a_class() {
+        let generator = generator();
+        let mut config = generator.get_config().clone();
+        config.funnel.occupations = vec![crate::funnel::Occupation {
+            name: "Farmer".to_string(),
+            item: crate::Item::from("Pitchfork".to_string()),
+        }];
+        let generator = CharacterGenerator::from_config(config);
+        let mut character = generator
+            .generate_funnel_character(&mut seeded_rng(1), &RollSpec::default(), &Constraints::default())
+            .unwrap();
+        let hit_points = character.hit_points;
+
+        let gained = generator.level_up(&mut seeded_rng(5), &mut character).unwrap();
+        assert_eq!(character.level, 1);
+        assert_eq!(character.classes.len(), 1);
+        assert_eq!(character.classes[0].templates, 1);
+        assert_eq!(character.features, generator.class_features(&character.classes[0].name, 1));
+        assert_eq!(character.magic.is_some(), character.classes[0].name == "Wizard");
+
+        // 4 base HP, plus the Fighter's template bonus
+        let bonus = if character.classes[0].name == "Fighter" { 2 } else { 0 };
+        assert_eq!(gained.hit_points, 4 + bonus);
+        assert_eq!(character.hit_points, hit_points + 4 + bonus);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{MAX_LEVEL, MIN_LEVEL};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+// Levels to generate characters at: a single level ("2"), a range ("1-3")
+// or a weighted list ("1:3,2:1" is mostly level 1 with an occasional 2).
+// Every level in a range gets the entry's weight, 1 if none is given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelSpec {
+    entries: Vec<LevelEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct LevelEntry {
+    low: u8,
+    high: u8,
+    weight: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelSpecError {
+    Empty,
+    Invalid(String),
+    OutOfRange { level: u64 },
+    Backwards { low: u8, high: u8 },
+    BadWeight(String),
+    NoChance,
+    Overflow,
+}
+
+impl fmt::Display for LevelSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelSpecError::Empty => write!(f, "no levels given"),
+            LevelSpecError::Invalid(entry) => write!(f, "invalid level entry '{}'", entry),
+            LevelSpecError::OutOfRange { level } => {
+                write!(f, "level {} is outside {} to {}", level, MIN_LEVEL, MAX_LEVEL)
+            }
+            LevelSpecError::Backwards { low, high } => write!(f, "level range {}-{} runs backwards", low, high),
+            LevelSpecError::BadWeight(weight) => write!(f, "level weight '{}' must be a number of zero or more", weight),
+            LevelSpecError::NoChance => write!(f, "level weights must add up to more than zero"),
+            LevelSpecError::Overflow => write!(f, "level weights add up to more than can be counted"),
+        }
+    }
+}
+
+impl std::error::Error for LevelSpecError {}
+
+impl LevelSpec {
+    // Each level that can come up, with its weight
+    pub fn levels(&self) -> Vec<(u8, f64)> {
+        self.entries
+            .iter()
+            .flat_map(|entry| (entry.low..=entry.high).map(move |level| (level, entry.weight.unwrap_or(1.0))))
+            .collect()
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> u8 {
+        let levels = self.levels();
+        // A single level needs no roll, which keeps fixed-level output
+        // independent of this table
+        if let [(level, _)] = levels[..] {
+            return level;
+        }
+
+        let index = WeightedIndex::new(levels.iter().map(|(_, weight)| *weight))
+            .expect("level weights are checked when the spec is parsed");
+        levels[index.sample(rng)].0
+    }
+}
+
+impl From<u8> for LevelSpec {
+    fn from(level: u8) -> Self {
+        Self {
+            entries: vec![LevelEntry { low: level, high: level, weight: None }],
+        }
+    }
+}
+
+fn parse_level(text: &str, entry: &str) -> Result<u8, LevelSpecError> {
+    let level: u64 = text.trim().parse().map_err(|_| LevelSpecError::Invalid(entry.to_string()))?;
+    match u8::try_from(level) {
+        Ok(level) if (MIN_LEVEL..=MAX_LEVEL).contains(&level) => Ok(level),
+        _ => Err(LevelSpecError::OutOfRange { level }),
+    }
+}
+
+impl FromStr for LevelSpec {
+    type Err = LevelSpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(LevelSpecError::Empty);
+        }
+
+        let mut entries = Vec::new();
+        for entry in s.split(',') {
+            let entry = entry.trim();
+            let (levels, weight) = match entry.split_once(':') {
+                Some((levels, weight)) => {
+                    let weight = weight.trim();
+                    match weight.parse::<f64>() {
+                        Ok(value) if value.is_finite() && value >= 0.0 => (levels, Some(value)),
+                        _ => return Err(LevelSpecError::BadWeight(weight.to_string())),
+                    }
+                }
+                None => (entry, None),
+            };
+            let (low, high) = match levels.split_once('-') {
+                Some((low, high)) => (parse_level(low, entry)?, parse_level(high, entry)?),
+                None => {
+                    let level = parse_level(levels, entry)?;
+                    (level, level)
+                }
+            };
+            if low > high {
+                return Err(LevelSpecError::Backwards { low, high });
+            }
+            entries.push(LevelEntry { low, high, weight });
+        }
+
+        let spec = Self { entries };
+        let total = spec.levels().iter().map(|(_, weight)| weight).sum::<f64>();
+        if total <= 0.0 {
+            return Err(LevelSpecError::NoChance);
+        }
+        // `roll` relies on this: rand panics on a total it can't count
+        if !total.is_finite() {
+            return Err(LevelSpecError::Overflow);
+        }
+
+        Ok(spec)
+    }
+}
+
+impl fmt::Display for LevelSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let levels = if entry.low == entry.high {
+                    entry.low.to_string()
+                } else {
+                    format!("{}-{}", entry.low, entry.high)
+                };
+                match entry.weight {
+                    Some(weight) => format!("{}:{}", levels, weight),
+                    None => levels,
+                }
+            })
+            .collect();
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded_rng;
+
+    #[test]
+    fn test_parse_and_display() {
+        for spec in ["3", "1-3", "1:3,2:1", "1-2:4,5"] {
+            assert_eq!(spec.parse::<LevelSpec>().unwrap().to_string(), spec);
+        }
+        assert_eq!(" 2 - 4 ".parse::<LevelSpec>().unwrap().to_string(), "2-4");
+
+        let spec: LevelSpec = "1-2:4,5".parse().unwrap();
+        assert_eq!(spec.levels(), vec![(1, 4.0), (2, 4.0), (5, 1.0)]);
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        assert_eq!("".parse::<LevelSpec>(), Err(LevelSpecError::Empty));
+        assert_eq!("x".parse::<LevelSpec>(), Err(LevelSpecError::Invalid("x".to_string())));
+        assert_eq!("0".parse::<LevelSpec>(), Err(LevelSpecError::OutOfRange { level: 0 }));
+        assert_eq!("1-11".parse::<LevelSpec>(), Err(LevelSpecError::OutOfRange { level: 11 }));
+        assert_eq!("3-1".parse::<LevelSpec>(), Err(LevelSpecError::Backwards { low: 3, high: 1 }));
+        assert_eq!("1:-2".parse::<LevelSpec>(), Err(LevelSpecError::BadWeight("-2".to_string())));
+        assert_eq!("1:0,2:0".parse::<LevelSpec>(), Err(LevelSpecError::NoChance));
+        assert_eq!("1:1e308,2:1e308".parse::<LevelSpec>(), Err(LevelSpecError::Overflow));
+    }
+
+    #[test]
+    fn test_roll_follows_weights() {
+        let mut rng = seeded_rng(5);
+        let spec: LevelSpec = "1:9,2:1,3:0".parse().unwrap();
+        let rolls: Vec<u8> = (0..1000).map(|_| spec.roll(&mut rng)).collect();
+
+        let ones = rolls.iter().filter(|&&level| level == 1).count();
+        assert!(ones > 800, "{} ones", ones);
+        assert!(rolls.contains(&2));
+        assert!(!rolls.contains(&3));
+
+        let range: LevelSpec = "4-6".parse().unwrap();
+        let rolls: Vec<u8> = (0..300).map(|_| range.roll(&mut rng)).collect();
+        for level in 4..=6 {
+            assert!(rolls.contains(&level));
+        }
+    }
+}
@@ -1,23 +1,668 @@
-use rand::Rng;
-use serde::{Deserialize, Serialize};
+pub mod abilities;
+pub mod dice;
+pub mod funnel;
+pub mod inventory;
+pub mod levels;
+pub mod magic;
+pub mod multiclass;
+pub mod names;
+pub mod level_up;
+pub mod party;
+
+use abilities::{Ability, AbilityRules};
+use dice::RollSpec;
+use funnel::FunnelRules;
+use inventory::Inventory;
+use levels::LevelSpec;
+use multiclass::MulticlassPolicy;
+use names::NameRules;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::de::{self, value::MapAccessDeserializer, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::marker::PhantomData;
+
+// Seeds are kept within 53 bits so they survive a round trip through
+// JSON (web) and TOML (saved files) unchanged
+pub const MAX_SEED: u64 = (1 << 53) - 1;
+
+// GLOG v2 characters run from level 1 to 10
+pub const MIN_LEVEL: u8 = 1;
+pub const MAX_LEVEL: u8 = 10;
+
+// Class templates A-D are gained at levels 1-4
+pub const MAX_TEMPLATES: u8 = 4;
+pub const TEMPLATE_NAMES: [&str; MAX_TEMPLATES as usize] = ["A", "B", "C", "D"];
+
+// Ability score method used when none is given
+pub const DEFAULT_METHOD: &str = "3d6";
+
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen_range(0..=MAX_SEED)
+}
+
+// ChaCha8 rather than StdRng, whose algorithm may change between rand
+// releases and platforms, so that saved seeds keep giving the same characters
+pub fn seeded_rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
+    #[serde(deserialize_with = "names_or_records")]
+    pub species: Vec<Species>,
+    #[serde(deserialize_with = "names_or_records")]
+    pub classes: Vec<Class>,
+    // Legacy list of Wizard archetypes, moved into the Wizard class's
+    // subclasses when the config is loaded
+    #[serde(default)]
+    pub wizard_archetypes: Vec<String>,
+    #[serde(default)]
+    pub hit_points: HitPointRules,
+    #[serde(default)]
+    pub class_templates: HashMap<String, ClassTemplates>,
+    #[serde(default)]
+    pub multiclass: MulticlassPolicy,
+    // How ability scores are rolled, bought or handed out
+    #[serde(default)]
+    pub abilities: AbilityRules,
+    #[serde(default)]
+    pub settings: HashMap<String, Setting>,
+    // Shared random-pick tables for classes that don't define their own
+    #[serde(default)]
+    pub equipment: EquipmentTables,
+    #[serde(default)]
+    pub backgrounds: Vec<Background>,
+    // Names for species that don't define their own
+    #[serde(default)]
+    pub names: NameRules,
+    // Quirks for species that don't define their own
+    #[serde(default)]
+    pub quirks: Vec<String>,
+    // Occupations and HP for level 0 funnel characters
+    #[serde(default)]
+    pub funnel: FunnelRules,
+}
+
+// Frequency table for one campaign setting. Entries named here replace the
+// weight given in their own record; anything not named keeps its weight.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Setting {
+    pub species: HashMap<String, f64>,
+    pub classes: HashMap<String, f64>,
+    // Subclass weights, keyed by class name and then subclass name
+    pub subclasses: HashMap<String, HashMap<String, f64>>,
+}
+
+// Limits on what a generated character can be. Empty include lists allow
+// anything; exclusions always apply.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Constraints {
     pub species: Vec<String>,
+    pub exclude_species: Vec<String>,
     pub classes: Vec<String>,
-    pub wizard_archetypes: Vec<String>,
+    pub exclude_classes: Vec<String>,
+    pub subclasses: Vec<String>,
+    pub exclude_subclasses: Vec<String>,
+}
+
+impl Constraints {
+    fn allows(include: &[String], exclude: &[String], name: &str) -> bool {
+        (include.is_empty() || include.iter().any(|n| n == name)) && !exclude.iter().any(|n| n == name)
+    }
+    
+    pub fn allows_species(&self, species: &Species) -> bool {
+        Self::allows(&self.species, &self.exclude_species, &species.name)
+    }
+    
+    pub fn allows_subclass(&self, subclass: &Subclass) -> bool {
+        Self::allows(&self.subclasses, &self.exclude_subclasses, &subclass.name)
+    }
+    
+    // A class is only allowed if it can also satisfy the subclass limits
+    pub fn allows_class(&self, class: &Class) -> bool {
+        if !Self::allows(&self.classes, &self.exclude_classes, &class.name) {
+            return false;
+        }
+        
+        if class.subclasses.is_empty() {
+            self.subclasses.is_empty()
+        } else {
+            class.subclasses.iter().any(|subclass| self.allows_subclass(subclass))
+        }
+    }
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl Config {
+    pub fn class(&self, name: &str) -> Option<&Class> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+
+    // Subclass names are looked up across every class, first match wins
+    pub fn subclass(&self, name: &str) -> Option<&Subclass> {
+        self.classes
+            .iter()
+            .flat_map(|class| &class.subclasses)
+            .find(|subclass| subclass.name == name)
+    }
+    
+    pub fn species_weight(&self, setting: Option<&str>, species: &Species) -> f64 {
+        setting
+            .and_then(|setting| self.settings.get(setting))
+            .and_then(|setting| setting.species.get(&species.name))
+            .copied()
+            .unwrap_or(species.weight)
+    }
+    
+    pub fn class_weight(&self, setting: Option<&str>, class: &Class) -> f64 {
+        setting
+            .and_then(|setting| self.settings.get(setting))
+            .and_then(|setting| setting.classes.get(&class.name))
+            .copied()
+            .unwrap_or(class.weight)
+    }
+    
+    pub fn subclass_weight(&self, setting: Option<&str>, class: &Class, subclass: &Subclass) -> f64 {
+        setting
+            .and_then(|setting| self.settings.get(setting))
+            .and_then(|setting| setting.subclasses.get(&class.name))
+            .and_then(|subclasses| subclasses.get(&subclass.name))
+            .copied()
+            .unwrap_or(subclass.weight)
+    }
+    
+    // Each table from the class record, falling back to the shared [equipment] table
+    pub fn equipment_tables_for<'a>(&'a self, class: &'a Class) -> [&'a [Item]; 4] {
+        let own = class.equipment.tables();
+        let shared = self.equipment.tables();
+        [0, 1, 2, 3].map(|i| if own[i].is_empty() { shared[i] } else { own[i] })
+    }
+    
+    // Names from the species record, falling back to the shared [names] table
+    pub fn names_for<'a>(&'a self, species: &'a Species) -> &'a NameRules {
+        if species.names.is_empty() {
+            &self.names
+        } else {
+            &species.names
+        }
+    }
+    
+    // Quirks from the species record, falling back to the shared quirks list
+    pub fn quirks_for<'a>(&'a self, species: &'a Species) -> &'a [String] {
+        if species.quirks.is_empty() {
+            &self.quirks
+        } else {
+            &species.quirks
+        }
+    }
+    
+    // Templates from the class record, falling back to the [class_templates] table
+    pub fn templates_for(&self, class: &str) -> Option<&ClassTemplates> {
+        self.class(class)
+            .and_then(|class| class.templates.as_ref())
+            .or_else(|| self.class_templates.get(class))
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Species {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub traits: Vec<String>,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    #[serde(default)]
+    pub names: NameRules,
+    #[serde(default)]
+    pub ability_adjustments: AbilityAdjustments,
+    // One of these is rolled for each character of the species
+    #[serde(default)]
+    pub quirks: Vec<String>,
+}
+
+// Bonuses and penalties a species applies to rolled ability scores
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AbilityAdjustments {
+    pub strength: i8,
+    pub dexterity: i8,
+    pub constitution: i8,
+    pub intelligence: i8,
+    pub wisdom: i8,
+    pub charisma: i8,
+}
+
+impl From<String> for Species {
+    fn from(name: String) -> Self {
+        Self {
+            name,
+            description: String::new(),
+            traits: Vec::new(),
+            weight: default_weight(),
+            names: NameRules::default(),
+            ability_adjustments: AbilityAdjustments::default(),
+            quirks: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Class {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, deserialize_with = "names_or_records")]
+    pub starting_gear: Vec<Item>,
+    #[serde(default)]
+    pub equipment: EquipmentTables,
+    pub templates: Option<ClassTemplates>,
+    // Archetypes, origins, orders and so on, one of which is picked for
+    // every character of this class
+    #[serde(default, deserialize_with = "names_or_records")]
+    pub subclasses: Vec<Subclass>,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    // Abilities that get the best scores, most important first, when scores
+    // are arranged
+    #[serde(default)]
+    pub ability_priority: Vec<Ability>,
+}
+
+impl From<String> for Class {
+    fn from(name: String) -> Self {
+        Self {
+            name,
+            description: String::new(),
+            starting_gear: Vec::new(),
+            equipment: EquipmentTables::default(),
+            templates: None,
+            subclasses: Vec::new(),
+            weight: default_weight(),
+            ability_priority: Vec::new(),
+        }
+    }
+}
+
+// A subclass with a spell list (a wizard archetype, say) makes its
+// characters spellcasters
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Subclass {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    #[serde(default, deserialize_with = "names_or_records")]
+    pub spells: Vec<Spell>,
+    #[serde(default)]
+    pub cantrips: Vec<String>,
+    #[serde(default)]
+    pub perks: Vec<String>,
+    #[serde(default)]
+    pub drawbacks: Vec<String>,
+    #[serde(default)]
+    pub mishaps: Vec<String>,
+    #[serde(default)]
+    pub dooms: Vec<String>,
+}
+
+impl From<String> for Subclass {
+    fn from(name: String) -> Self {
+        Self {
+            name,
+            description: String::new(),
+            weight: default_weight(),
+            spells: Vec::new(),
+            cantrips: Vec::new(),
+            perks: Vec::new(),
+            drawbacks: Vec::new(),
+            mishaps: Vec::new(),
+            dooms: Vec::new(),
+        }
+    }
+}
+
+// A piece of equipment. Plain names in the config are 1-slot items with no
+// combat stats.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Item {
+    pub name: String,
+    #[serde(default = "default_slots")]
+    pub slots: u8,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub armor: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub damage: Option<RollSpec>,
+    // Small enough to hang from a belt quick-draw slot
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub quick_draw: bool,
+}
+
+fn default_slots() -> u8 {
+    1
+}
+
+fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl From<String> for Item {
+    fn from(name: String) -> Self {
+        Self {
+            name,
+            slots: default_slots(),
+            armor: 0,
+            damage: None,
+            quick_draw: false,
+        }
+    }
+}
+
+// e.g. "Chain armor (2 slots, +4 armor)"
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut details = Vec::new();
+        if self.slots != 1 {
+            details.push(format!("{} slots", self.slots));
+        }
+        if self.armor > 0 {
+            details.push(format!("+{} armor", self.armor));
+        }
+        if let Some(damage) = &self.damage {
+            details.push(format!("{} damage", damage));
+        }
+        
+        if details.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{} ({})", self.name, details.join(", "))
+        }
+    }
+}
+
+// Tables a character gets one random item from each of
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct EquipmentTables {
+    #[serde(deserialize_with = "names_or_records")]
+    pub weapons: Vec<Item>,
+    #[serde(deserialize_with = "names_or_records")]
+    pub armor: Vec<Item>,
+    #[serde(deserialize_with = "names_or_records")]
+    pub tools: Vec<Item>,
+    #[serde(deserialize_with = "names_or_records")]
+    pub trinkets: Vec<Item>,
+}
+
+impl EquipmentTables {
+    pub fn tables(&self) -> [&[Item]; 4] {
+        [&self.weapons, &self.armor, &self.tools, &self.trinkets]
+    }
+}
+
+// What a character did before adventuring. Only the name and skill are kept
+// on the character; the items go into their inventory.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Background {
+    pub name: String,
+    pub skill: String,
+    #[serde(default, deserialize_with = "names_or_records", skip_serializing)]
+    pub items: Vec<Item>,
+    // Species that can have this background; empty means any
+    #[serde(default, skip_serializing)]
+    pub species: Vec<String>,
+}
+
+impl Background {
+    pub fn allows_species(&self, species: &str) -> bool {
+        self.species.is_empty() || self.species.iter().any(|name| name == species)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Spell {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+}
+
+impl From<String> for Spell {
+    fn from(name: String) -> Self {
+        Self { name, text: String::new() }
+    }
+}
+
+// The class that the legacy wizard_archetypes list belongs to
+const LEGACY_ARCHETYPE_CLASS: &str = "Wizard";
+
+// Config lists accept plain names ("Human") as well as full records
+// ({ name = "Human", ... }) so older config files keep working. A record is
+// handed straight to its own Deserialize so its errors say which field is
+// wrong.
+struct NameOrRecord<T>(T);
+
+impl<'de, T> Deserialize<'de> for NameOrRecord<T>
+where
+    T: Deserialize<'de> + From<String>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NameOrRecordVisitor(PhantomData)).map(NameOrRecord)
+    }
+}
+
+struct NameOrRecordVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for NameOrRecordVisitor<T>
+where
+    T: Deserialize<'de> + From<String>,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a name or a record")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<T, E> {
+        Ok(T::from(name.to_string()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        T::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+fn names_or_records<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + From<String>,
+{
+    let entries = Vec::<NameOrRecord<T>>::deserialize(deserializer)?;
+    
+    Ok(entries.into_iter().map(|NameOrRecord(entry)| entry).collect())
+}
+
+fn name_or_record<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + From<String>,
+{
+    NameOrRecord::<T>::deserialize(deserializer).map(|NameOrRecord(entry)| entry)
+}
+
+// The features granted by each of a class's four templates
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ClassTemplates {
+    #[serde(rename = "A", default)]
+    pub a: Vec<Feature>,
+    #[serde(rename = "B", default)]
+    pub b: Vec<Feature>,
+    #[serde(rename = "C", default)]
+    pub c: Vec<Feature>,
+    #[serde(rename = "D", default)]
+    pub d: Vec<Feature>,
+}
+
+impl ClassTemplates {
+    // Features of the template at `index`, where 0 is template A
+    pub fn features(&self, index: usize) -> &[Feature] {
+        match index {
+            0 => &self.a,
+            1 => &self.b,
+            2 => &self.c,
+            3 => &self.d,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Feature {
+    pub name: String,
+    pub text: String,
+}
+
+// A feature a character has gained, tagged with the class and template it
+// came from
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ClassFeature {
+    pub class: String,
+    pub template: String,
+    pub name: String,
+    pub text: String,
+}
+
+// Max HP is base + Con bonus, plus a roll for every level after the first
+// and a flat bonus for each template of the character's class
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HitPointRules {
+    pub base: u8,
+    pub per_level: RollSpec,
+    pub template_bonus: HashMap<String, u8>,
+}
+
+impl Default for HitPointRules {
+    fn default() -> Self {
+        Self {
+            base: 4,
+            per_level: RollSpec::new(1, 6, 0).expect("1d6 is a valid roll"),
+            template_bonus: HashMap::new(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct Character {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub level: u8,
-    pub class: String,
+    // Every class the character has templates in, starting class first
+    pub classes: Vec<CharacterClass>,
     pub species: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub traits: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirk: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<Background>,
+    // Only level 0 funnel characters have an occupation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occupation: Option<String>,
+    pub hit_points: u16,
     pub ability_scores: AbilityScores,
+    pub derived_stats: DerivedStats,
+    pub features: Vec<ClassFeature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub magic: Option<Magic>,
+    pub inventory: Inventory,
+}
+
+impl Character {
+    // e.g. "Fighter / Wizard (Necromancer)"
+    pub fn class_name(&self) -> String {
+        self.classes.iter().map(ToString::to_string).collect::<Vec<_>>().join(" / ")
+    }
+    
+    // Every template taken, e.g. ["Fighter A", "Fighter B", "Thief A"]
+    pub fn templates(&self) -> Vec<String> {
+        self.classes
+            .iter()
+            .flat_map(|class| {
+                TEMPLATE_NAMES
+                    .iter()
+                    .take(usize::from(class.templates))
+                    .map(move |template| format!("{} {}", class.name, template))
+            })
+            .collect()
+    }
+}
+
+// One class a character has taken, and how many of its templates
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CharacterClass {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subclass: Option<String>,
+    pub templates: u8,
+}
+
+// e.g. "Wizard (Necromancer)"
+impl fmt::Display for CharacterClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.subclass {
+            Some(subclass) => write!(f, "{} ({})", self.name, subclass),
+            None => write!(f, "{}", self.name),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Clone)]
+// Spellcasting for characters whose subclass has a spell list
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Magic {
+    pub magic_dice: u8,
+    pub spells: Vec<Spell>,
+    pub cantrips: Vec<String>,
+    pub perks: Vec<String>,
+    pub drawbacks: Vec<String>,
+}
+
+// Casters get one Magic Die per template of their casting class
+pub fn magic_dice_for_templates(templates: u8) -> u8 {
+    templates.min(MAX_TEMPLATES)
+}
+
+// Casters start with two spells at template A and learn one more with each
+// template after that
+pub fn spells_known_for_templates(templates: u8) -> usize {
+    usize::from(templates.min(MAX_TEMPLATES)) + 1
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct AbilityScores {
     pub strength: u8,
     pub dexterity: u8,
@@ -27,50 +672,203 @@ pub struct AbilityScores {
     pub charisma: u8,
 }
 
+impl AbilityScores {
+    pub fn total(&self) -> u32 {
+        [self.strength, self.dexterity, self.constitution, self.intelligence, self.wisdom, self.charisma]
+            .into_iter()
+            .map(u32::from)
+            .sum()
+    }
+    
+    pub fn adjust(&mut self, adjustments: &AbilityAdjustments) {
+        self.strength = self.strength.saturating_add_signed(adjustments.strength);
+        self.dexterity = self.dexterity.saturating_add_signed(adjustments.dexterity);
+        self.constitution = self.constitution.saturating_add_signed(adjustments.constitution);
+        self.intelligence = self.intelligence.saturating_add_signed(adjustments.intelligence);
+        self.wisdom = self.wisdom.saturating_add_signed(adjustments.wisdom);
+        self.charisma = self.charisma.saturating_add_signed(adjustments.charisma);
+    }
+}
+
+// GLOG ability bonus table: 3-4 is -2, 5-7 is -1, 8-13 is +0, 14-16 is +1
+// and 17-18 is +2, with anything beyond that range at -3/+3
+pub fn ability_modifier(score: u8) -> i8 {
+    match score {
+        0..=2 => -3,
+        3..=4 => -2,
+        5..=7 => -1,
+        8..=13 => 0,
+        14..=16 => 1,
+        17..=18 => 2,
+        _ => 3,
+    }
+}
+
+// Values a character rolls against in play, each a base plus the bonus of
+// the ability that drives it
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct DerivedStats {
+    pub attack: i8,
+    pub defense: i8,
+    pub movement: i8,
+    pub stealth: i8,
+    pub save: i8,
+}
+
+pub const BASE_ATTACK: i8 = 10;
+pub const BASE_DEFENSE: i8 = 10;
+pub const BASE_MOVEMENT: i8 = 12;
+pub const BASE_STEALTH: i8 = 5;
+pub const BASE_SAVE: i8 = 5;
+
+impl DerivedStats {
+    pub fn from_ability_scores(scores: &AbilityScores) -> Self {
+        Self {
+            attack: BASE_ATTACK + ability_modifier(scores.strength),
+            defense: BASE_DEFENSE + ability_modifier(scores.dexterity),
+            movement: BASE_MOVEMENT + ability_modifier(scores.dexterity),
+            stealth: BASE_STEALTH + ability_modifier(scores.dexterity),
+            save: BASE_SAVE + ability_modifier(scores.wisdom),
+        }
+    }
+    
+    // Each point of encumbrance costs 1 Stealth and 1 Movement
+    pub fn apply_encumbrance(&mut self, inventory: &Inventory) {
+        let penalty = i8::try_from(inventory.encumbrance()).unwrap_or(i8::MAX);
+        self.stealth = self.stealth.saturating_sub(penalty);
+        self.movement = self.movement.saturating_sub(penalty);
+    }
+    
+    // Armor and shields carried add their armor to Defense
+    pub fn apply_armor(&mut self, inventory: &Inventory) {
+        let armor = i8::try_from(inventory.armor()).unwrap_or(i8::MAX);
+        self.defense = self.defense.saturating_add(armor);
+    }
+}
+
+pub fn templates_at_level(level: u8) -> u8 {
+    level.min(MAX_TEMPLATES)
+}
+
+#[derive(Clone)]
 pub struct CharacterGenerator {
     config: Config,
+    setting: Option<String>,
 }
 
 impl CharacterGenerator {
     pub fn new(config_path: &str) -> anyhow::Result<Self> {
         let config = Self::load_config(config_path)?;
         
-        Ok(Self { config })
+        Ok(Self { config, setting: None })
     }
     
-    pub fn from_config(config: Config) -> Self {
-        Self { config }
+    pub fn from_config(mut config: Config) -> Self {
+        Self::apply_legacy_archetypes(&mut config);
+        
+        Self { config, setting: None }
     }
     
-    pub fn generate_character(&self, level: u8, dice: u8, faces: u8, lowest: u8) -> anyhow::Result<Character> {
-        if level < 1 || level > 10 {
-            return Err(anyhow::anyhow!("Level must be between 1 and 10"));
+    // Use the named setting's frequency table when picking entries
+    pub fn with_setting(mut self, setting: &str) -> anyhow::Result<Self> {
+        if !self.config.settings.contains_key(setting) {
+            return Err(anyhow::anyhow!("Unknown setting: {}", setting));
         }
         
-        let mut rng = rand::thread_rng();
+        self.setting = Some(setting.to_string());
+        Ok(self)
+    }
+    
+    pub fn generate_character(&self, rng: &mut impl Rng, level: u8, method: &RollSpec, constraints: &Constraints) -> anyhow::Result<Character> {
+        self.build_character(rng, level, method, constraints, None)
+    }
+    
+    // Like `generate_character`, but starting in `starting_class` when one is
+    // given instead of a random allowed class
+    fn build_character(
+        &self,
+        rng: &mut impl Rng,
+        level: u8,
+        method: &RollSpec,
+        constraints: &Constraints,
+        starting_class: Option<&Class>,
+    ) -> anyhow::Result<Character> {
+        if !(MIN_LEVEL..=MAX_LEVEL).contains(&level) {
+            return Err(anyhow::anyhow!("Level must be between {} and {}", MIN_LEVEL, MAX_LEVEL));
+        }
         
         // Generate random species and class
-        let species = self.config.species[rng.gen_range(0..self.config.species.len())].clone();
-        let mut class = self.config.classes[rng.gen_range(0..self.config.classes.len())].clone();
+        let setting = self.setting.as_deref();
+        self.validate_constraints(constraints)?;
+        let species = choose_allowed(
+            rng,
+            "species",
+            &self.config.species,
+            |species| constraints.allows_species(species),
+            |species| self.config.species_weight(setting, species),
+        )?;
+        let class_record = match starting_class {
+            Some(class) => class,
+            None => choose_allowed(
+                rng,
+                "class",
+                &self.config.classes,
+                |class| constraints.allows_class(class),
+                |class| self.config.class_weight(setting, class),
+            )?,
+        };
+        let name = self.config.names_for(species).generate(rng);
+        let quirk = self.config.quirks_for(species).choose(rng).cloned();
+        let background = self.roll_background(rng, &species.name);
         
-        // If wizard is selected, add an archetype
-        if class == "Wizard" {
-            let archetype = &self.config.wizard_archetypes[rng.gen_range(0..self.config.wizard_archetypes.len())];
-            class = format!("Wizard ({})", archetype);
+        // Template A comes from the starting class; every template after it
+        // may branch out into a new class, by the multiclass policy
+        let mut classes = vec![self.start_class(rng, class_record, constraints)?];
+        for _ in 1..templates_at_level(level) {
+            self.take_template(rng, &mut classes, constraints)?;
         }
         
-        // Generate ability scores
-        let ability_scores = Self::generate_ability_scores(&mut rng, dice, faces, lowest);
+        // Generate ability scores, adjusted for species before anything is
+        // worked out from them
+        let mut ability_scores = self.generate_ability_scores(rng, method, &class_record.ability_priority)?;
+        ability_scores.adjust(&species.ability_adjustments);
+        let hit_points = self.roll_hit_points(rng, &classes, level, &ability_scores);
+        let mut derived_stats = DerivedStats::from_ability_scores(&ability_scores);
+        let features = classes
+            .iter()
+            .flat_map(|class| self.class_features(&class.name, class.templates))
+            .collect();
+        let magic = self
+            .casting_class(&classes)
+            .and_then(|(class, subclass)| Self::roll_magic(rng, subclass, class.templates));
+        let mut items = self.roll_inventory(rng, class_record);
+        if let Some(background) = &background {
+            items.extend(background.items.iter().cloned());
+        }
+        let inventory = Inventory::with_items(&ability_scores, items);
+        derived_stats.apply_encumbrance(&inventory);
+        derived_stats.apply_armor(&inventory);
         
         Ok(Character {
+            name,
             level,
-            class,
-            species,
+            classes,
+            species: species.name.clone(),
+            traits: species.traits.clone(),
+            quirk,
+            background,
+            occupation: None,
+            hit_points,
             ability_scores,
+            derived_stats,
+            features,
+            magic,
+            inventory,
         })
     }
     
-    pub fn generate_characters(&self, level: u8, count: u8, dice: u8, faces: u8, lowest: u8) -> anyhow::Result<Vec<Character>> {
+    // Each character rolls its own level from `levels`
+    pub fn generate_characters(&self, rng: &mut impl Rng, levels: &LevelSpec, count: u8, method: &RollSpec, constraints: &Constraints) -> anyhow::Result<Vec<Character>> {
         if count < 1 {
             return Err(anyhow::anyhow!("Must generate at least 1 character"));
         }
@@ -82,12 +880,36 @@ impl CharacterGenerator {
         let mut characters = Vec::new();
         
         for _ in 0..count {
-            characters.push(self.generate_character(level, dice, faces, lowest)?);
+            let level = levels.roll(rng);
+            characters.push(self.generate_character(rng, level, method, constraints)?);
         }
         
         Ok(characters)
     }
     
+    // Constraints must name things that exist, so typos fail loudly
+    fn validate_constraints(&self, constraints: &Constraints) -> anyhow::Result<()> {
+        for species in constraints.species.iter().chain(&constraints.exclude_species) {
+            if !self.config.species.iter().any(|s| &s.name == species) {
+                return Err(anyhow::anyhow!("Unknown species: {}", species));
+            }
+        }
+        
+        for class in constraints.classes.iter().chain(&constraints.exclude_classes) {
+            if self.config.class(class).is_none() {
+                return Err(anyhow::anyhow!("Unknown class: {}", class));
+            }
+        }
+        
+        for subclass in constraints.subclasses.iter().chain(&constraints.exclude_subclasses) {
+            if !self.config.classes.iter().flat_map(|c| &c.subclasses).any(|s| &s.name == subclass) {
+                return Err(anyhow::anyhow!("Unknown subclass: {}", subclass));
+            }
+        }
+        
+        Ok(())
+    }
+    
     pub fn get_config(&self) -> &Config {
         &self.config
     }
@@ -96,10 +918,11 @@ impl CharacterGenerator {
         let content = fs::read_to_string(filename)
             .map_err(|_| anyhow::anyhow!("Could not read config file: {}", filename))?;
         
-        let config: Config = toml::from_str(&content)
+        let mut config: Config = toml::from_str(&content)
             .map_err(|e| anyhow::anyhow!("Invalid config file format: {}", e))?;
         
         Self::validate_config(&config)?;
+        Self::apply_legacy_archetypes(&mut config);
         
         Ok(config)
     }
@@ -113,74 +936,404 @@ impl CharacterGenerator {
             return Err(anyhow::anyhow!("Config file must contain at least one class"));
         }
         
+        if !config.wizard_archetypes.is_empty() {
+            match config.class(LEGACY_ARCHETYPE_CLASS) {
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "wizard_archetypes given but there is no {} class",
+                        LEGACY_ARCHETYPE_CLASS
+                    ))
+                }
+                Some(class) if !class.subclasses.is_empty() => {
+                    return Err(anyhow::anyhow!(
+                        "{} subclasses are given both in its class record and in wizard_archetypes",
+                        LEGACY_ARCHETYPE_CLASS
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        
+        for class in &config.classes {
+            Self::validate_names(
+                &format!("{} subclass", class.name),
+                class.subclasses.iter().map(|subclass| &subclass.name),
+            )?;
+            abilities::validate_ability_priority(class)?;
+        }
+        
+        Self::validate_weights(config, None)?;
+        for (name, setting) in &config.settings {
+            for species in setting.species.keys() {
+                if !config.species.iter().any(|s| &s.name == species) {
+                    return Err(anyhow::anyhow!("Setting {} weights unknown species: {}", name, species));
+                }
+            }
+            for class in setting.classes.keys() {
+                if config.class(class).is_none() {
+                    return Err(anyhow::anyhow!("Setting {} weights unknown class: {}", name, class));
+                }
+            }
+            for (class, subclasses) in &setting.subclasses {
+                let Some(record) = config.class(class) else {
+                    return Err(anyhow::anyhow!("Setting {} weights subclasses of unknown class: {}", name, class));
+                };
+                for subclass in subclasses.keys() {
+                    if !record.subclasses.iter().any(|s| &s.name == subclass) {
+                        return Err(anyhow::anyhow!("Setting {} weights unknown {} subclass: {}", name, class, subclass));
+                    }
+                }
+            }
+            Self::validate_weights(config, Some(name))?;
+        }
+        
+        Self::validate_names("species", config.species.iter().map(|species| &species.name))?;
+        Self::validate_names("class", config.classes.iter().map(|class| &class.name))?;
+        Self::validate_names("background", config.backgrounds.iter().map(|background| &background.name))?;
+        Self::validate_names("occupation", config.funnel.occupations.iter().map(|occupation| &occupation.name))?;
+        
+        config.multiclass.validate().map_err(|e| anyhow::anyhow!("Invalid multiclass policy: {}", e))?;
+        config.abilities.validate().map_err(|e| anyhow::anyhow!("Invalid ability rules: {}", e))?;
+        config.names.validate().map_err(|e| anyhow::anyhow!("Invalid names table: {}", e))?;
+        for species in &config.species {
+            species
+                .names
+                .validate()
+                .map_err(|e| anyhow::anyhow!("Invalid names for species {}: {}", species.name, e))?;
+        }
+        
+        for background in &config.backgrounds {
+            if !(1..=2).contains(&background.items.len()) {
+                return Err(anyhow::anyhow!("Background {} must give one or two items", background.name));
+            }
+            for species in &background.species {
+                if !config.species.iter().any(|s| &s.name == species) {
+                    return Err(anyhow::anyhow!("Background {} is limited to unknown species: {}", background.name, species));
+                }
+            }
+        }
+        
+        for class in config.hit_points.template_bonus.keys() {
+            if config.class(class).is_none() {
+                return Err(anyhow::anyhow!("Hit point bonus given for unknown class: {}", class));
+            }
+        }
+        
+        for class in config.class_templates.keys() {
+            match config.class(class) {
+                None => return Err(anyhow::anyhow!("Templates given for unknown class: {}", class)),
+                Some(record) if record.templates.is_some() => {
+                    return Err(anyhow::anyhow!("Templates for class {} are defined twice", class))
+                }
+                Some(_) => {}
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // Older configs list Wizard archetypes at the top level; treat them as
+    // the Wizard class's subclasses
+    fn apply_legacy_archetypes(config: &mut Config) {
         if config.wizard_archetypes.is_empty() {
-            return Err(anyhow::anyhow!("Config file must contain at least one wizard archetype"));
+            return;
+        }
+        
+        if let Some(class) = config.classes.iter_mut().find(|class| class.name == LEGACY_ARCHETYPE_CLASS) {
+            if class.subclasses.is_empty() {
+                class.subclasses = config.wizard_archetypes.drain(..).map(Subclass::from).collect();
+            }
+        }
+    }
+    
+    // Every weight must be non-negative and every list must have a positive
+    // total, with or without the given setting applied
+    fn validate_weights(config: &Config, setting: Option<&str>) -> anyhow::Result<()> {
+        let context = match setting {
+            Some(setting) => format!(" in setting {}", setting),
+            None => String::new(),
+        };
+        
+        let check = |kind: &str, weights: Vec<(&String, f64)>| -> anyhow::Result<()> {
+            for (name, weight) in &weights {
+                if !weight.is_finite() || *weight < 0.0 {
+                    return Err(anyhow::anyhow!("Weight of {} {}{} must be zero or more", kind, name, context));
+                }
+            }
+            let total = weights.iter().map(|(_, weight)| weight).sum::<f64>();
+            if total <= 0.0 {
+                return Err(anyhow::anyhow!("{} weights{} must add up to more than zero", kind, context));
+            }
+            // Each weight can be finite and the total still overflow
+            if !total.is_finite() {
+                return Err(anyhow::anyhow!("{} weights{} add up to more than can be counted", kind, context));
+            }
+            Ok(())
+        };
+        
+        check(
+            "species",
+            config.species.iter().map(|s| (&s.name, config.species_weight(setting, s))).collect(),
+        )?;
+        check(
+            "class",
+            config.classes.iter().map(|c| (&c.name, config.class_weight(setting, c))).collect(),
+        )?;
+        for class in config.classes.iter().filter(|class| !class.subclasses.is_empty()) {
+            check(
+                &format!("{} subclass", class.name),
+                class
+                    .subclasses
+                    .iter()
+                    .map(|s| (&s.name, config.subclass_weight(setting, class, s)))
+                    .collect(),
+            )?;
         }
         
         Ok(())
     }
     
-    fn generate_ability_scores(rng: &mut impl Rng, dice: u8, faces: u8, lowest: u8) -> AbilityScores {
-        AbilityScores {
-            strength: Self::roll_ability_score(rng, dice, faces, lowest),
-            dexterity: Self::roll_ability_score(rng, dice, faces, lowest),
-            constitution: Self::roll_ability_score(rng, dice, faces, lowest),
-            intelligence: Self::roll_ability_score(rng, dice, faces, lowest),
-            wisdom: Self::roll_ability_score(rng, dice, faces, lowest),
-            charisma: Self::roll_ability_score(rng, dice, faces, lowest),
+    // Entries are looked up by name, so names must be present and unique
+    fn validate_names<'a>(kind: &str, names: impl Iterator<Item = &'a String>) -> anyhow::Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for name in names {
+            if name.trim().is_empty() {
+                return Err(anyhow::anyhow!("Config file contains a {} with an empty name", kind));
+            }
+            if !seen.insert(name) {
+                return Err(anyhow::anyhow!("Config file lists {} {} more than once", kind, name));
+            }
         }
+        
+        Ok(())
     }
     
-    pub fn roll_ability_score(rng: &mut impl Rng, dice: u8, faces: u8, lowest: u8) -> u8 {
-        // Roll 3d6
-        let mut rolls: Vec<u8> = (0..dice).map(|_| rng.gen_range(1..=faces)).collect();
-        rolls.sort_unstable();
-
-        // need to cast 'lowest' to usize to use in a slice
-        let lowest = lowest as usize;
-        rolls[lowest..].iter().sum()
+    pub fn roll_ability_score(rng: &mut impl Rng, method: &RollSpec) -> u8 {
+        method.roll(rng)
+    }
+    
+    // Every feature from the class's first `templates` templates, in order
+    fn class_features(&self, class: &str, templates: u8) -> Vec<ClassFeature> {
+        (0..templates.min(MAX_TEMPLATES) as usize)
+            .flat_map(|index| self.template_features(class, index))
+            .collect()
+    }
+    
+    // Features of a single template, where 0 is template A
+    fn template_features(&self, class: &str, index: usize) -> Vec<ClassFeature> {
+        let Some(templates) = self.config.templates_for(class) else {
+            return Vec::new();
+        };
+        
+        templates
+            .features(index)
+            .iter()
+            .map(|feature| ClassFeature {
+                class: class.to_string(),
+                template: TEMPLATE_NAMES[index].to_string(),
+                name: feature.name.clone(),
+                text: feature.text.clone(),
+            })
+            .collect()
+    }
+    
+    fn roll_magic(rng: &mut impl Rng, subclass: &Subclass, templates: u8) -> Option<Magic> {
+        if subclass.spells.is_empty() {
+            return None;
+        }
+        
+        let spells = subclass
+            .spells
+            .choose_multiple(rng, spells_known_for_templates(templates))
+            .cloned()
+            .collect();
+        
+        Some(Magic {
+            magic_dice: magic_dice_for_templates(templates),
+            spells,
+            cantrips: subclass.cantrips.clone(),
+            perks: subclass.perks.clone(),
+            drawbacks: subclass.drawbacks.clone(),
+        })
+    }
+    
+    // Any background open to the species, or none if the config has none
+    fn roll_background(&self, rng: &mut impl Rng, species: &str) -> Option<Background> {
+        let candidates: Vec<&Background> = self
+            .config
+            .backgrounds
+            .iter()
+            .filter(|background| background.allows_species(species))
+            .collect();
+        
+        candidates.choose(rng).map(|&background| background.clone())
+    }
+    
+    // The class's guaranteed kit plus one pick from each equipment table
+    fn roll_inventory(&self, rng: &mut impl Rng, class: &Class) -> Vec<Item> {
+        let mut inventory = class.starting_gear.clone();
+        for table in self.config.equipment_tables_for(class) {
+            inventory.extend(table.choose(rng).cloned());
+        }
+        
+        inventory
+    }
+    
+    fn roll_hit_points(&self, rng: &mut impl Rng, classes: &[CharacterClass], level: u8, ability_scores: &AbilityScores) -> u16 {
+        let rules = &self.config.hit_points;
+        
+        let mut hit_points = i32::from(rules.base) + i32::from(ability_modifier(ability_scores.constitution));
+        for _ in 1..level {
+            hit_points += i32::from(rules.per_level.roll(rng));
+        }
+        for class in classes {
+            hit_points += i32::from(self.template_hit_points(&class.name)) * i32::from(class.templates);
+        }
+        
+        // Even the sickliest character starts with 1 HP
+        hit_points.clamp(1, i32::from(u16::MAX)) as u16
+    }
+    
+    // Flat HP for each template of the class
+    fn template_hit_points(&self, class: &str) -> u8 {
+        self.config.hit_points.template_bonus.get(class).copied().unwrap_or(0)
     }
 }
 
-// Utility functions for file operations
-pub fn save_characters_to_file(characters: &[Character], level: u8, count: u8) -> anyhow::Result<String> {
-    let filename = format!("characters_level_{}_count_{}.toml", level, count);
+// Pick an item with probability proportional to its weight
+fn choose_weighted<'a, T>(rng: &mut impl Rng, items: &'a [T], weight: impl Fn(&T) -> f64) -> anyhow::Result<&'a T> {
+    // rand panics rather than erroring when the total overflows
+    if !items.iter().map(&weight).sum::<f64>().is_finite() {
+        return Err(anyhow::anyhow!("Cannot pick from weighted list: weights add up to more than can be counted"));
+    }
+    let index = WeightedIndex::new(items.iter().map(weight))
+        .map_err(|e| anyhow::anyhow!("Cannot pick from weighted list: {}", e))?;
     
-    // Create a wrapper struct to hold all characters
-    #[derive(Serialize)]
-    struct CharacterCollection<'a> {
-        characters: &'a [Character],
+    Ok(&items[index.sample(rng)])
+}
+
+// Pick a weighted item among those allowed, failing if the constraints leave
+// nothing with a chance of being picked
+fn choose_allowed<'a, T>(
+    rng: &mut impl Rng,
+    kind: &str,
+    items: &'a [T],
+    allowed: impl Fn(&T) -> bool,
+    weight: impl Fn(&T) -> f64,
+) -> anyhow::Result<&'a T> {
+    let candidates: Vec<&T> = items.iter().filter(|item| allowed(item)).collect();
+    if candidates.iter().all(|item| weight(item) <= 0.0) {
+        return Err(anyhow::anyhow!("No {} is left to choose from with the given constraints", kind));
     }
     
-    let collection = CharacterCollection { characters };
-    let content = toml::to_string_pretty(&collection)?;
-    fs::write(&filename, content)?;
+    Ok(*choose_weighted(rng, &candidates, |item| weight(item))?)
+}
+
+// The layout of a saved characters file: the seed the characters were first
+// rolled with and the seed of every level-up since. The other options those
+// runs were given aren't kept, so the seeds alone don't regenerate the file.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CharacterFile {
+    pub seed: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub level_ups: Vec<LevelUpRecord>,
+    // For a funnel, the player each character belongs to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub players: Vec<u8>,
+    pub characters: Vec<Character>,
+}
+
+// One level-up run against a characters file: its seed, and the character
+// it was limited to (counting from 1), if any
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LevelUpRecord {
+    pub seed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub character: Option<u64>,
+}
+
+// Named after the levels the characters actually rolled, e.g.
+// characters_level_1-3_count_5.toml
+pub fn save_characters_to_file(characters: &[Character], seed: u64) -> anyhow::Result<String> {
+    let lowest = characters.iter().map(|character| character.level).min().unwrap_or(MIN_LEVEL);
+    let highest = characters.iter().map(|character| character.level).max().unwrap_or(MIN_LEVEL);
+    let levels = if lowest == highest {
+        lowest.to_string()
+    } else {
+        format!("{}-{}", lowest, highest)
+    };
+    let filename = format!("characters_level_{}_count_{}.toml", levels, characters.len());
+    
+    let file = CharacterFile {
+        seed,
+        level_ups: Vec::new(),
+        players: Vec::new(),
+        characters: characters.to_vec(),
+    };
+    write_characters_file(&filename, &file)?;
     
     Ok(filename)
 }
 
+pub fn load_characters_file(filename: &str) -> anyhow::Result<CharacterFile> {
+    let content = fs::read_to_string(filename)
+        .map_err(|_| anyhow::anyhow!("Could not read characters file: {}", filename))?;
+    
+    toml::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid characters file {}: {}", filename, e))
+}
+
+pub fn write_characters_file(filename: &str, file: &CharacterFile) -> anyhow::Result<()> {
+    let content = toml::to_string_pretty(file)?;
+    fs::write(filename, content)?;
+    
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
     fn create_test_config() -> Config {
         Config {
-            species: vec!["Human".to_string(), "Elf".to_string()],
-            classes: vec!["Fighter".to_string(), "Wizard".to_string()],
-            wizard_archetypes: vec!["Necromancer".to_string(), "Pyromancer".to_string()],
+            species: vec![Species::from("Human".to_string()), Species::from("Elf".to_string())],
+            classes: vec![
+                Class::from("Fighter".to_string()),
+                Class {
+                    subclasses: vec![
+                        Subclass::from("Necromancer".to_string()),
+                        Subclass::from("Pyromancer".to_string()),
+                    ],
+                    ..Class::from("Wizard".to_string())
+                },
+            ],
+            wizard_archetypes: Vec::new(),
+            hit_points: HitPointRules::default(),
+            class_templates: HashMap::new(),
+            multiclass: MulticlassPolicy::default(),
+            abilities: AbilityRules::default(),
+            settings: HashMap::new(),
+            equipment: EquipmentTables::default(),
+            backgrounds: Vec::new(),
+            names: NameRules::default(),
+            quirks: Vec::new(),
+            funnel: FunnelRules::default(),
         }
     }
     
+    fn three_d_six() -> RollSpec {
+        DEFAULT_METHOD.parse().unwrap()
+    }
+    
     #[test]
     fn test_character_generation() {
         let config = create_test_config();
         let generator = CharacterGenerator::from_config(config);
         
-        let character = generator.generate_character(5).unwrap();
+        let character = generator.generate_character(&mut rand::thread_rng(), 5, &three_d_six(), &Constraints::default()).unwrap();
         
         assert_eq!(character.level, 5);
         assert!(!character.species.is_empty());
-        assert!(!character.class.is_empty());
+        assert!(!character.class_name().is_empty());
         assert!(character.ability_scores.strength >= 3 && character.ability_scores.strength <= 18);
     }
     
@@ -189,7 +1342,7 @@ mod tests {
         let config = create_test_config();
         let generator = CharacterGenerator::from_config(config);
         
-        let characters = generator.generate_characters(3, 5).unwrap();
+        let characters = generator.generate_characters(&mut rand::thread_rng(), &LevelSpec::from(3), 5, &three_d_six(), &Constraints::default()).unwrap();
         
         assert_eq!(characters.len(), 5);
         assert!(characters.iter().all(|c| c.level == 3));
@@ -200,8 +1353,9 @@ mod tests {
         let config = create_test_config();
         let generator = CharacterGenerator::from_config(config);
         
-        assert!(generator.generate_character(0).is_err());
-        assert!(generator.generate_character(21).is_err());
+        let mut rng = rand::thread_rng();
+        assert!(generator.generate_character(&mut rng, 0, &three_d_six(), &Constraints::default()).is_err());
+        assert!(generator.generate_character(&mut rng, 21, &three_d_six(), &Constraints::default()).is_err());
     }
     
     #[test]
@@ -210,13 +1364,816 @@ mod tests {
         let generator = CharacterGenerator::from_config(config);
         
         // Generate many characters to eventually get a wizard
+        let mut rng = seeded_rng(1);
+        let mut found = false;
         for _ in 0..100 {
-            let character = generator.generate_character(1).unwrap();
-            if character.class.starts_with("Wizard") {
-                assert!(character.class.contains("("));
-                assert!(character.class.contains(")"));
+            let character = generator.generate_character(&mut rng, 1, &three_d_six(), &Constraints::default()).unwrap();
+            if character.class_name().starts_with("Wizard") {
+                assert!(character.class_name().contains("("));
+                assert!(character.class_name().contains(")"));
+                found = true;
                 break;
             }
         }
+        assert!(found);
+    }
+    
+    #[test]
+    fn test_same_seed_same_characters() {
+        let config = create_test_config();
+        let generator = CharacterGenerator::from_config(config);
+        
+        let method: RollSpec = "4d6dl1".parse().unwrap();
+        let first = generator.generate_characters(&mut seeded_rng(42), &LevelSpec::from(2), 10, &method, &Constraints::default()).unwrap();
+        let second = generator.generate_characters(&mut seeded_rng(42), &LevelSpec::from(2), 10, &method, &Constraints::default()).unwrap();
+        
+        assert_eq!(first, second);
+    }
+    
+    #[test]
+    fn test_seeded_rng_is_stable() {
+        // Pinned so that a change of algorithm, which would change every
+        // saved seed's characters, fails here first
+        let mut rng = seeded_rng(1234);
+        let rolls: Vec<u32> = (0..4).map(|_| rng.gen_range(1..=6)).collect();
+        assert_eq!(rolls, [5, 3, 1, 6]);
+    }
+    
+    #[test]
+    fn test_generate_characters_count_limits() {
+        let generator = CharacterGenerator::from_config(create_test_config());
+        let mut rng = seeded_rng(0);
+        
+        assert!(generator.generate_characters(&mut rng, &LevelSpec::from(1), 0, &three_d_six(), &Constraints::default()).is_err());
+        assert!(generator.generate_characters(&mut rng, &LevelSpec::from(1), 101, &three_d_six(), &Constraints::default()).is_err());
+        assert_eq!(generator.generate_characters(&mut rng, &LevelSpec::from(1), 100, &three_d_six(), &Constraints::default()).unwrap().len(), 100);
+    }
+    
+    #[test]
+    fn test_drop_lowest_keeps_highest_dice() {
+        let spec = RollSpec::new(4, 6, 1).unwrap();
+        
+        for seed in 0..200 {
+            // Replay the same raw dice from an identically seeded RNG
+            let mut raw = seeded_rng(seed);
+            let mut rolls: Vec<u32> = (0..4).map(|_| raw.gen_range(1..=6)).collect();
+            rolls.sort_unstable();
+            let expected: u32 = rolls[1..].iter().sum();
+            
+            let score = CharacterGenerator::roll_ability_score(&mut seeded_rng(seed), &spec);
+            assert_eq!(u32::from(score), expected);
+        }
+    }
+    
+    // Mean and variance of many seeded rolls of a spec
+    fn roll_statistics(spec: &RollSpec, rolls: usize) -> (f64, f64) {
+        let mut rng = seeded_rng(2024);
+        let scores: Vec<f64> = (0..rolls)
+            .map(|_| f64::from(CharacterGenerator::roll_ability_score(&mut rng, spec)))
+            .collect();
+        let mean = scores.iter().sum::<f64>() / rolls as f64;
+        let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / rolls as f64;
+        
+        (mean, variance)
+    }
+    
+    #[test]
+    fn test_3d6_distribution() {
+        let (mean, variance) = roll_statistics(&RollSpec::new(3, 6, 0).unwrap(), 50_000);
+        
+        // 3d6 has mean 10.5 and variance 8.75
+        assert!((mean - 10.5).abs() < 0.1, "mean was {}", mean);
+        assert!((variance - 8.75).abs() < 0.3, "variance was {}", variance);
+    }
+    
+    #[test]
+    fn test_4d6_drop_lowest_distribution() {
+        let (mean, variance) = roll_statistics(&RollSpec::new(4, 6, 1).unwrap(), 50_000);
+        let (plain_mean, _) = roll_statistics(&RollSpec::new(3, 6, 0).unwrap(), 50_000);
+        
+        // 4d6 drop lowest has mean ~12.24 and variance ~8.12
+        assert!((mean - 12.24).abs() < 0.1, "mean was {}", mean);
+        assert!((variance - 8.12).abs() < 0.3, "variance was {}", variance);
+        assert!(mean > plain_mean + 1.5);
+    }
+    
+    // Write a config file into a per-test temporary directory
+    fn write_temp_config(name: &str, content: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("glog-test-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, content).unwrap();
+        path
+    }
+    
+    #[test]
+    fn test_load_valid_config() {
+        let path = write_temp_config(
+            "valid",
+            "species = [\"Ratling\"]\nclasses = [\"Wizard\"]\nwizard_archetypes = [\"Drowned\"]\n",
+        );
+        let generator = CharacterGenerator::new(path.to_str().unwrap()).unwrap();
+        
+        assert_eq!(generator.get_config().species, vec![Species::from("Ratling".to_string())]);
+        let character = generator.generate_character(&mut seeded_rng(0), 1, &three_d_six(), &Constraints::default()).unwrap();
+        assert_eq!(character.class_name(), "Wizard (Drowned)");
+        
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+    
+    #[test]
+    fn test_missing_config_file() {
+        let err = CharacterGenerator::new("does/not/exist.toml").err().unwrap();
+        assert!(err.to_string().contains("Could not read config file"));
+    }
+    
+    #[test]
+    fn test_malformed_config_file() {
+        let path = write_temp_config("malformed", "species = [\"Ratling\"\nclasses = ");
+        let err = CharacterGenerator::new(path.to_str().unwrap()).err().unwrap();
+        assert!(err.to_string().contains("Invalid config file format"));
+        
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        
+        let path = write_temp_config("missing-field", "species = [\"Ratling\"]\n");
+        let err = CharacterGenerator::new(path.to_str().unwrap()).err().unwrap();
+        assert!(err.to_string().contains("Invalid config file format"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+    
+    #[test]
+    fn test_config_validation() {
+        let mut config = create_test_config();
+        config.species.clear();
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+        
+        let mut config = create_test_config();
+        config.classes.clear();
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+        
+        // Classes without subclasses are fine, even the Wizard
+        let mut config = create_test_config();
+        config.classes[1].subclasses.clear();
+        assert!(CharacterGenerator::validate_config(&config).is_ok());
+        
+        assert!(CharacterGenerator::validate_config(&create_test_config()).is_ok());
+    }
+    
+    #[test]
+    fn test_ability_modifier_table() {
+        let expected = [(3, -2), (4, -2), (5, -1), (7, -1), (8, 0), (13, 0), (14, 1), (16, 1), (17, 2), (18, 2)];
+        for (score, modifier) in expected {
+            assert_eq!(ability_modifier(score), modifier, "score {}", score);
+        }
+        assert_eq!(ability_modifier(1), -3);
+        assert_eq!(ability_modifier(20), 3);
+    }
+    
+    #[test]
+    fn test_hit_points() {
+        let mut config = create_test_config();
+        config.hit_points.per_level = "2".parse().unwrap();
+        config.hit_points.template_bonus.insert("Fighter".to_string(), 2);
+        let generator = CharacterGenerator::from_config(config);
+        let mut rng = seeded_rng(0);
+        
+        let mut scores = AbilityScores {
+            strength: 10,
+            dexterity: 10,
+            constitution: 14,
+            intelligence: 10,
+            wisdom: 10,
+            charisma: 10,
+        };
+        
+        let taken = |name: &str, templates| CharacterClass {
+            name: name.to_string(),
+            subclass: None,
+            templates,
+        };
+        
+        // 4 base + 1 Con + 2 levels at 2 each + 3 templates at 2 each
+        assert_eq!(generator.roll_hit_points(&mut rng, &[taken("Fighter", 3)], 3, &scores), 15);
+        // Templates stop at D, levels keep adding HP
+        assert_eq!(generator.roll_hit_points(&mut rng, &[taken("Fighter", 4)], 6, &scores), 23);
+        assert_eq!(generator.roll_hit_points(&mut rng, &[taken("Wizard", 1)], 1, &scores), 5);
+        // Only the Fighter's templates carry the bonus
+        assert_eq!(generator.roll_hit_points(&mut rng, &[taken("Wizard", 1), taken("Fighter", 2)], 3, &scores), 13);
+        
+        scores.constitution = 3;
+        assert_eq!(generator.roll_hit_points(&mut rng, &[taken("Wizard", 1)], 1, &scores), 2);
+    }
+    
+    #[test]
+    fn test_hit_point_bonus_for_unknown_class() {
+        let mut config = create_test_config();
+        config.hit_points.template_bonus.insert("Bard".to_string(), 1);
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+    }
+    
+    #[test]
+    fn test_derived_stats() {
+        let scores = AbilityScores {
+            strength: 14,
+            dexterity: 4,
+            constitution: 10,
+            intelligence: 10,
+            wisdom: 17,
+            charisma: 10,
+        };
+        
+        assert_eq!(
+            DerivedStats::from_ability_scores(&scores),
+            DerivedStats {
+                attack: 11,
+                defense: 8,
+                movement: 10,
+                stealth: 3,
+                save: 7,
+            }
+        );
+    }
+    
+    fn feature(name: &str) -> Feature {
+        Feature {
+            name: name.to_string(),
+            text: format!("{} rules", name),
+        }
+    }
+    
+    #[test]
+    fn test_class_features_by_level() {
+        let mut config = create_test_config();
+        config.class_templates.insert(
+            "Fighter".to_string(),
+            ClassTemplates {
+                a: vec![feature("Parry"), feature("Trained")],
+                b: vec![feature("Cleave")],
+                c: vec![],
+                d: vec![feature("Second Wind")],
+            },
+        );
+        let generator = CharacterGenerator::from_config(config);
+        
+        let names = |level| -> Vec<(String, String)> {
+            generator
+                .class_features("Fighter", level)
+                .into_iter()
+                .map(|f| (f.template, f.name))
+                .collect()
+        };
+        
+        assert_eq!(names(1), vec![("A".to_string(), "Parry".to_string()), ("A".to_string(), "Trained".to_string())]);
+        assert_eq!(names(2).len(), 3);
+        assert_eq!(names(4).last().unwrap(), &("D".to_string(), "Second Wind".to_string()));
+        assert_eq!(names(10).len(), 4);
+        assert!(generator.class_features("Wizard", 4).is_empty());
+    }
+    
+    #[test]
+    fn test_templates_for_unknown_class() {
+        let mut config = create_test_config();
+        config.class_templates.insert("Bard".to_string(), ClassTemplates::default());
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+    }
+    
+    #[test]
+    fn test_structured_config_records() {
+        let config: Config = toml::from_str(
+            r#"
+            species = ["Human", { name = "Ratling", traits = ["Keen nose"] }]
+            wizard_archetypes = ["Drowned"]
+
+            [[classes]]
+            name = "Fighter"
+            description = "Trained warrior"
+            starting_gear = ["Sword", "Shield"]
+
+            [classes.templates]
+            A = [{ name = "Parry", text = "Reduce damage" }]
+
+            [[classes]]
+            name = "Wizard"
+
+            [class_templates.Wizard]
+            A = [{ name = "Spellcasting", text = "Gain a Magic Die" }]
+            "#,
+        )
+        .unwrap();
+        CharacterGenerator::validate_config(&config).unwrap();
+        
+        assert_eq!(config.species[0], Species::from("Human".to_string()));
+        assert_eq!(config.species[1].traits, vec!["Keen nose".to_string()]);
+        
+        let fighter = config.class("Fighter").unwrap();
+        assert_eq!(fighter.starting_gear, vec![Item::from("Sword".to_string()), Item::from("Shield".to_string())]);
+        assert_eq!(config.templates_for("Fighter").unwrap().a[0].name, "Parry");
+        assert_eq!(config.templates_for("Wizard").unwrap().a[0].name, "Spellcasting");
+    }
+    
+    #[test]
+    fn test_record_errors_name_the_field() {
+        let error = |config: &str| toml::from_str::<Config>(config).unwrap_err().to_string();
+
+        let weight = error(r#"
+            species = [{ name = "Ratling", weight = "x" }]
+            classes = ["Fighter"]
+        "#);
+        assert!(weight.contains("invalid type: string \"x\", expected f64"), "{}", weight);
+
+        let damage = error(r#"
+            species = ["Human"]
+            classes = [{ name = "Fighter", starting_gear = [{ name = "Sword", damage = "3d0" }] }]
+        "#);
+        assert!(damage.contains("dice must have at least one face"), "{}", damage);
+
+        let typo = error(r#"
+            species = [{ name = "Ratling", trait = ["Keen nose"] }]
+            classes = ["Fighter"]
+        "#);
+        assert!(typo.contains("unknown field `trait`"), "{}", typo);
+
+        let typo = error(r#"
+            species = ["Human"]
+            classes = [{ name = "Wizard", subclasses = [{ name = "Drowned", wieght = 5 }] }]
+        "#);
+        assert!(typo.contains("unknown field `wieght`"), "{}", typo);
+    }
+    
+    #[test]
+    fn test_duplicate_and_conflicting_records() {
+        let mut config = create_test_config();
+        config.species.push(Species::from("Elf".to_string()));
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+        
+        let mut config = create_test_config();
+        config.classes[0].templates = Some(ClassTemplates::default());
+        config.class_templates.insert("Fighter".to_string(), ClassTemplates::default());
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+    }
+    
+    #[test]
+    fn test_subclasses_for_any_class() {
+        let mut config = create_test_config();
+        config.classes = vec![Class {
+            subclasses: vec![Subclass::from("Order of the Rose".to_string())],
+            ..Class::from("Knight".to_string())
+        }];
+        let generator = CharacterGenerator::from_config(config);
+        assert!(generator.get_config().subclass("Order of the Rose").is_some());
+        assert!(generator.get_config().subclass("Necromancer").is_none());
+        
+        let character = generator.generate_character(&mut seeded_rng(0), 1, &three_d_six(), &Constraints::default()).unwrap();
+        assert_eq!(character.class_name(), "Knight (Order of the Rose)");
+    }
+    
+    #[test]
+    fn test_legacy_wizard_archetypes() {
+        let mut config = create_test_config();
+        config.classes[1].subclasses.clear();
+        config.wizard_archetypes = vec!["Drowned".to_string()];
+        CharacterGenerator::validate_config(&config).unwrap();
+        
+        let generator = CharacterGenerator::from_config(config.clone());
+        let subclasses = &generator.get_config().class("Wizard").unwrap().subclasses;
+        assert_eq!(subclasses, &vec![Subclass::from("Drowned".to_string())]);
+        
+        // Archetypes can't be given twice, or for a config without a Wizard
+        config.classes[1].subclasses = vec![Subclass::from("Garden".to_string())];
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+        
+        config.classes.remove(1);
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+    }
+    
+    #[test]
+    fn test_weighted_selection() {
+        let mut config = create_test_config();
+        config.species[0].weight = 9.0;
+        config.classes[0].weight = 0.0;
+        config.classes[1].subclasses[1].weight = 3.0;
+        let generator = CharacterGenerator::from_config(config);
+        
+        let mut rng = seeded_rng(11);
+        let characters = generator.generate_characters(&mut rng, &LevelSpec::from(1), 100, &three_d_six(), &Constraints::default()).unwrap();
+        
+        // Zero-weight classes never come up; weighted entries dominate
+        assert!(characters.iter().all(|c| c.class_name().starts_with("Wizard")));
+        let humans = characters.iter().filter(|c| c.species == "Human").count();
+        let pyromancers = characters.iter().filter(|c| c.class_name() == "Wizard (Pyromancer)").count();
+        assert!(humans > 80, "{} humans", humans);
+        assert!(pyromancers > 60, "{} pyromancers", pyromancers);
+    }
+    
+    #[test]
+    fn test_setting_frequency_table() {
+        let mut config = create_test_config();
+        config.settings.insert(
+            "Elfhame".to_string(),
+            Setting {
+                species: HashMap::from([("Human".to_string(), 0.0)]),
+                classes: HashMap::from([("Wizard".to_string(), 0.0)]),
+                subclasses: HashMap::new(),
+            },
+        );
+        CharacterGenerator::validate_config(&config).unwrap();
+        
+        let generator = CharacterGenerator::from_config(config).with_setting("Elfhame").unwrap();
+        let characters = generator.generate_characters(&mut seeded_rng(3), &LevelSpec::from(1), 50, &three_d_six(), &Constraints::default()).unwrap();
+        assert!(characters.iter().all(|c| c.species == "Elf" && c.class_name() == "Fighter"));
+        
+        assert!(CharacterGenerator::from_config(create_test_config()).with_setting("Nowhere").is_err());
+    }
+    
+    #[test]
+    fn test_invalid_weights() {
+        let mut config = create_test_config();
+        config.species[0].weight = -1.0;
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+        
+        let mut config = create_test_config();
+        config.classes[0].weight = 0.0;
+        config.classes[1].weight = 0.0;
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+        
+        // A setting can't zero out a whole list either
+        let mut config = create_test_config();
+        config.settings.insert(
+            "Empty".to_string(),
+            Setting {
+                species: HashMap::from([("Human".to_string(), 0.0), ("Elf".to_string(), 0.0)]),
+                ..Setting::default()
+            },
+        );
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+        
+        let mut config = create_test_config();
+        config.settings.insert(
+            "Typo".to_string(),
+            Setting {
+                classes: HashMap::from([("Figther".to_string(), 2.0)]),
+                ..Setting::default()
+            },
+        );
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+        
+        // Every weight is finite but the total isn't
+        let mut config = create_test_config();
+        config.species[0].weight = 1e308;
+        config.species[1].weight = 1e308;
+        let err = CharacterGenerator::validate_config(&config).unwrap_err();
+        assert_eq!(err.to_string(), "species weights add up to more than can be counted");
+        let generator = CharacterGenerator::from_config(config);
+        assert!(generator.generate_character(&mut seeded_rng(0), 1, &three_d_six(), &Constraints::default()).is_err());
+    }
+    
+    #[test]
+    fn test_pinned_and_excluded_choices() {
+        let generator = CharacterGenerator::from_config(create_test_config());
+        let mut rng = seeded_rng(5);
+        
+        let constraints = Constraints {
+            species: vec!["Elf".to_string()],
+            exclude_classes: vec!["Wizard".to_string()],
+            ..Constraints::default()
+        };
+        let characters = generator.generate_characters(&mut rng, &LevelSpec::from(1), 30, &three_d_six(), &constraints).unwrap();
+        assert!(characters.iter().all(|c| c.species == "Elf" && c.class_name() == "Fighter"));
+        
+        // Pinning a subclass implies its class
+        let constraints = Constraints {
+            subclasses: vec!["Pyromancer".to_string()],
+            ..Constraints::default()
+        };
+        let characters = generator.generate_characters(&mut rng, &LevelSpec::from(1), 30, &three_d_six(), &constraints).unwrap();
+        assert!(characters.iter().all(|c| c.class_name() == "Wizard (Pyromancer)"));
+        
+        let constraints = Constraints {
+            exclude_subclasses: vec!["Pyromancer".to_string()],
+            ..Constraints::default()
+        };
+        let characters = generator.generate_characters(&mut rng, &LevelSpec::from(1), 30, &three_d_six(), &constraints).unwrap();
+        assert!(characters.iter().all(|c| c.class_name() != "Wizard (Pyromancer)"));
+    }
+    
+    #[test]
+    fn test_impossible_constraints() {
+        let generator = CharacterGenerator::from_config(create_test_config());
+        let mut rng = seeded_rng(5);
+        
+        let constraints = Constraints {
+            classes: vec!["Fighter".to_string()],
+            exclude_classes: vec!["Fighter".to_string()],
+            ..Constraints::default()
+        };
+        let err = generator.generate_character(&mut rng, 1, &three_d_six(), &constraints).unwrap_err();
+        assert_eq!(err.to_string(), "No class is left to choose from with the given constraints");
+        
+        let constraints = Constraints {
+            classes: vec!["Fighter".to_string()],
+            subclasses: vec!["Necromancer".to_string()],
+            ..Constraints::default()
+        };
+        assert!(generator.generate_character(&mut rng, 1, &three_d_six(), &constraints).is_err());
+        
+        let constraints = Constraints {
+            species: vec!["Dwarf".to_string()],
+            ..Constraints::default()
+        };
+        let err = generator.generate_character(&mut rng, 1, &three_d_six(), &constraints).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown species: Dwarf");
+    }
+    
+    #[test]
+    fn test_spellcaster_magic() {
+        let mut config = create_test_config();
+        let necromancer = &mut config.classes[1].subclasses[0];
+        necromancer.spells = ["Raise Corpse", "Bone Spear", "Speak with Dead", "Death Mask", "Ghoul Touch", "Wither"]
+            .into_iter()
+            .map(|name| Spell::from(name.to_string()))
+            .collect();
+        necromancer.cantrips = vec!["Smell death".to_string()];
+        necromancer.perks = vec!["Undead ignore you".to_string()];
+        necromancer.drawbacks = vec!["Healing magic hurts you".to_string()];
+        let generator = CharacterGenerator::from_config(config);
+        
+        let constraints = Constraints {
+            subclasses: vec!["Necromancer".to_string()],
+            ..Constraints::default()
+        };
+        for level in [1, 3, 7] {
+            let character = generator.generate_character(&mut seeded_rng(9), level, &three_d_six(), &constraints).unwrap();
+            let magic = character.magic.unwrap();
+            
+            assert_eq!(magic.magic_dice, templates_at_level(level));
+            assert_eq!(magic.spells.len(), spells_known_for_templates(templates_at_level(level)));
+            let mut names: Vec<&String> = magic.spells.iter().map(|spell| &spell.name).collect();
+            names.sort();
+            names.dedup();
+            assert_eq!(names.len(), magic.spells.len());
+            assert_eq!(magic.drawbacks, vec!["Healing magic hurts you".to_string()]);
+        }
+        
+        // Subclasses without spells don't cast
+        let constraints = Constraints {
+            subclasses: vec!["Pyromancer".to_string()],
+            ..Constraints::default()
+        };
+        let character = generator.generate_character(&mut seeded_rng(9), 2, &three_d_six(), &constraints).unwrap();
+        assert!(character.magic.is_none());
+    }
+    
+    #[test]
+    fn test_starting_inventory() {
+        let config: Config = toml::from_str(
+            r#"
+            species = ["Human"]
+
+            [[classes]]
+            name = "Fighter"
+            starting_gear = [{ name = "Shield", armor = 1 }, "Rope"]
+
+            [classes.equipment]
+            weapons = [{ name = "Spear", slots = 2, damage = "d8" }]
+
+            [equipment]
+            weapons = ["Club"]
+            trinkets = ["Lucky coin"]
+            "#,
+        )
+        .unwrap();
+        let generator = CharacterGenerator::from_config(config);
+        
+        let character = generator.generate_character(&mut seeded_rng(2), 1, &three_d_six(), &Constraints::default()).unwrap();
+        let names: Vec<&str> = character.inventory.items().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, vec!["Shield", "Rope", "Spear", "Lucky coin"]);
+        
+        let spear = &character.inventory.pack[2];
+        assert_eq!(spear.slots, 2);
+        assert_eq!(spear.damage, Some("1d8".parse().unwrap()));
+        assert_eq!(character.inventory.pack[0].armor, 1);
+        
+        // Only the stats an item actually has are written out
+        let saved = toml::to_string(&character).unwrap();
+        assert!(saved.contains("damage = \"1d8\""));
+        assert_eq!(saved.matches("armor = ").count(), 1);
+    }
+    
+    #[test]
+    fn test_encumbered_character() {
+        let mut config = create_test_config();
+        config.classes = vec![Class {
+            starting_gear: vec![
+                Item { slots: 3, ..Item::from("Anvil".to_string()) },
+                Item { quick_draw: true, ..Item::from("Dagger".to_string()) },
+            ],
+            ..Class::from("Smith".to_string())
+        }];
+        let generator = CharacterGenerator::from_config(config);
+        
+        // Strength 1 gives a single pack slot
+        let method: RollSpec = "1d1".parse().unwrap();
+        let character = generator.generate_character(&mut seeded_rng(0), 1, &method, &Constraints::default()).unwrap();
+        let inventory = &character.inventory;
+        assert_eq!(inventory.quick_draw[0].name, "Dagger");
+        assert_eq!(inventory.encumbrance(), 2);
+        
+        let unencumbered = DerivedStats::from_ability_scores(&character.ability_scores);
+        assert_eq!(character.derived_stats.stealth, unencumbered.stealth - 2);
+        assert_eq!(character.derived_stats.movement, unencumbered.movement - 2);
+        assert_eq!(character.derived_stats.attack, unencumbered.attack);
+    }
+    
+    #[test]
+    fn test_armor_adds_to_defense() {
+        let mut config = create_test_config();
+        config.classes = vec![Class {
+            starting_gear: vec![
+                Item { slots: 2, armor: 4, ..Item::from("Chain armor".to_string()) },
+                Item { armor: 1, ..Item::from("Shield".to_string()) },
+                Item { damage: Some("d8".parse().unwrap()), ..Item::from("Sword".to_string()) },
+            ],
+            ..Class::from("Knight".to_string())
+        }];
+        let generator = CharacterGenerator::from_config(config);
+        
+        let method: RollSpec = "3d6".parse().unwrap();
+        let character = generator.generate_character(&mut seeded_rng(0), 1, &method, &Constraints::default()).unwrap();
+        let unarmored = DerivedStats::from_ability_scores(&character.ability_scores);
+        assert_eq!(character.inventory.armor(), 5);
+        assert_eq!(character.derived_stats.defense, unarmored.defense + 5);
+    }
+    
+    #[test]
+    fn test_backgrounds() {
+        let mut config = create_test_config();
+        config.backgrounds = vec![
+            Background {
+                name: "Ratcatcher".to_string(),
+                skill: "Vermin".to_string(),
+                items: vec![Item::from("Rat trap".to_string())],
+                species: vec!["Elf".to_string()],
+            },
+            Background {
+                name: "Farmer".to_string(),
+                skill: "Livestock".to_string(),
+                items: vec![Item::from("Pitchfork".to_string()), Item::from("Seeds".to_string())],
+                species: Vec::new(),
+            },
+        ];
+        let generator = CharacterGenerator::from_config(config.clone());
+        
+        let humans = Constraints {
+            species: vec!["Human".to_string()],
+            ..Constraints::default()
+        };
+        for seed in 0..10 {
+            let character = generator.generate_character(&mut seeded_rng(seed), 1, &three_d_six(), &humans).unwrap();
+            let background = character.background.unwrap();
+            assert_eq!((background.name.as_str(), background.skill.as_str()), ("Farmer", "Livestock"));
+            assert!(character.inventory.items().any(|item| item.name == "Pitchfork"));
+        }
+        
+        // Without a backgrounds table characters simply have none
+        let generator = CharacterGenerator::from_config(create_test_config());
+        let character = generator.generate_character(&mut seeded_rng(0), 1, &three_d_six(), &humans).unwrap();
+        assert!(character.background.is_none());
+        
+        let mut bad = config.clone();
+        bad.backgrounds[0].items.clear();
+        assert!(CharacterGenerator::validate_config(&bad).is_err());
+        let mut bad = config;
+        bad.backgrounds[0].species = vec!["Dwarf".to_string()];
+        assert!(CharacterGenerator::validate_config(&bad).is_err());
+    }
+    
+    #[test]
+    fn test_species_names() {
+        let mut config = create_test_config();
+        config.species[1].names = NameRules {
+            given: vec!["Lirael".to_string()],
+            ..NameRules::default()
+        };
+        let generator = CharacterGenerator::from_config(config.clone());
+        
+        let only = |species: &str| Constraints {
+            species: vec![species.to_string()],
+            ..Constraints::default()
+        };
+        let elf = generator.generate_character(&mut seeded_rng(0), 1, &three_d_six(), &only("Elf")).unwrap();
+        assert_eq!(elf.name.as_deref(), Some("Lirael"));
+        let human = generator.generate_character(&mut seeded_rng(0), 1, &three_d_six(), &only("Human")).unwrap();
+        assert_eq!(human.name, None);
+        
+        // Species without names of their own use the shared table
+        config.names.given = vec!["Alys".to_string()];
+        let generator = CharacterGenerator::from_config(config.clone());
+        let human = generator.generate_character(&mut seeded_rng(0), 1, &three_d_six(), &only("Human")).unwrap();
+        assert_eq!(human.name.as_deref(), Some("Alys"));
+        
+        config.species[0].names.surnames = vec!["Smith".to_string()];
+        assert!(CharacterGenerator::validate_config(&config).is_err());
+    }
+    
+    #[test]
+    fn test_species_traits_and_adjustments() {
+        let config: Config = toml::from_str(
+            r#"
+            classes = ["Fighter"]
+            quirks = ["Whistles constantly"]
+
+            [[species]]
+            name = "Slugling"
+            traits = ["Leaves a slime trail"]
+            quirks = ["Shell is painted bright red"]
+            ability_adjustments = { strength = -2, constitution = 3 }
+
+            [[species]]
+            name = "Human"
+            "#,
+        )
+        .unwrap();
+        let generator = CharacterGenerator::from_config(config);
+        let only = |species: &str| Constraints {
+            species: vec![species.to_string()],
+            ..Constraints::default()
+        };
+        
+        let method: RollSpec = "10".parse().unwrap();
+        let slugling = generator.generate_character(&mut seeded_rng(0), 1, &method, &only("Slugling")).unwrap();
+        assert_eq!(slugling.traits, vec!["Leaves a slime trail".to_string()]);
+        assert_eq!(slugling.quirk.as_deref(), Some("Shell is painted bright red"));
+        assert_eq!(slugling.ability_scores.strength, 8);
+        assert_eq!(slugling.ability_scores.constitution, 13);
+        assert_eq!(slugling.ability_scores.dexterity, 10);
+        
+        // Derived stats and inventory use the adjusted scores
+        assert_eq!(slugling.derived_stats.attack, BASE_ATTACK + ability_modifier(8));
+        assert_eq!(slugling.inventory.capacity, 8);
+        
+        let human = generator.generate_character(&mut seeded_rng(0), 1, &method, &only("Human")).unwrap();
+        assert!(human.traits.is_empty());
+        assert_eq!(human.quirk.as_deref(), Some("Whistles constantly"));
+        assert_eq!(human.ability_scores.strength, 10);
+        
+        let typo = toml::from_str::<AbilityAdjustments>("strenght = 1");
+        assert!(typo.is_err());
+    }
+    
+    #[test]
+    fn test_level_ranges() {
+        let mut config = create_test_config();
+        config.class_templates.insert(
+            "Fighter".to_string(),
+            ClassTemplates {
+                a: vec![feature("Parry")],
+                b: vec![feature("Cleave")],
+                c: vec![feature("Mastery")],
+                d: vec![feature("Second Wind")],
+            },
+        );
+        let generator = CharacterGenerator::from_config(config);
+        let fighters = Constraints {
+            classes: vec!["Fighter".to_string()],
+            ..Constraints::default()
+        };
+        
+        let levels: LevelSpec = "2-4".parse().unwrap();
+        let characters = generator.generate_characters(&mut seeded_rng(8), &levels, 30, &three_d_six(), &fighters).unwrap();
+        let mut rolled: Vec<u8> = characters.iter().map(|character| character.level).collect();
+        rolled.sort();
+        rolled.dedup();
+        assert_eq!(rolled, vec![2, 3, 4]);
+        
+        // Each character is built for the level they rolled
+        for character in &characters {
+            assert_eq!(character.features.len(), usize::from(character.level));
+        }
+    }
+    
+    #[test]
+    fn test_saved_characters_load_back() {
+        let config_path = concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml");
+        let generator = CharacterGenerator::new(config_path).unwrap();
+        let levels: LevelSpec = "1-5".parse().unwrap();
+        let characters = generator.generate_characters(&mut seeded_rng(6), &levels, 20, &three_d_six(), &Constraints::default()).unwrap();
+        
+        let file = CharacterFile {
+            seed: 6,
+            level_ups: vec![LevelUpRecord { seed: 2, character: None }, LevelUpRecord { seed: 3, character: Some(4) }],
+            players: Vec::new(),
+            characters,
+        };
+        let mut loaded: CharacterFile = toml::from_str(&toml::to_string_pretty(&file).unwrap()).unwrap();
+        
+        // A background's items are saved in the inventory rather than with it
+        for (loaded, original) in loaded.characters.iter_mut().zip(&file.characters) {
+            if let (Some(loaded), Some(original)) = (&mut loaded.background, &original.background) {
+                assert!(loaded.items.is_empty());
+                loaded.items = original.items.clone();
+                loaded.species = original.species.clone();
+            }
+        }
+        assert_eq!(loaded, file);
     }
 }
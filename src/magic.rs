@@ -0,0 +1,113 @@
+use crate::Subclass;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+
+// Magic Dice are d6s. Dice showing 1-3 return to the caster's pool, 4-6
+// are spent until the next rest.
+pub const MAGIC_DIE_FACES: u8 = 6;
+pub const HIGHEST_RETURNING_FACE: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Casting {
+    pub dice: Vec<u8>,
+    pub sum: u32,
+    pub returned: usize,
+    pub outcome: CastOutcome,
+}
+
+// Doubles bring a mishap, triples (or better) bring doom
+#[derive(Debug, Clone, PartialEq)]
+pub enum CastOutcome {
+    Normal,
+    Mishap(String),
+    Doom(String),
+}
+
+pub fn roll_magic_dice(rng: &mut impl Rng, count: u8) -> Vec<u8> {
+    (0..count).map(|_| rng.gen_range(1..=MAGIC_DIE_FACES)).collect()
+}
+
+// Check a set of Magic Dice results for doubles and triples, rolling on the
+// subclass's mishap or doom table when they come up
+pub fn resolve_magic_dice(rng: &mut impl Rng, subclass: &Subclass, dice: &[u8]) -> anyhow::Result<Casting> {
+    if let Some(die) = dice.iter().find(|die| !(1..=MAGIC_DIE_FACES).contains(die)) {
+        return Err(anyhow::anyhow!("Magic Dice show 1 to {}, not {}", MAGIC_DIE_FACES, die));
+    }
+
+    let mut counts: HashMap<u8, usize> = HashMap::new();
+    for die in dice {
+        *counts.entry(*die).or_default() += 1;
+    }
+    let most_matching = counts.values().copied().max().unwrap_or(0);
+
+    let outcome = match most_matching {
+        0 | 1 => CastOutcome::Normal,
+        2 => CastOutcome::Mishap(roll_on_table(rng, &subclass.name, "mishap", &subclass.mishaps)?),
+        _ => CastOutcome::Doom(roll_on_table(rng, &subclass.name, "doom", &subclass.dooms)?),
+    };
+
+    Ok(Casting {
+        dice: dice.to_vec(),
+        sum: dice.iter().map(|&die| u32::from(die)).sum(),
+        returned: dice.iter().filter(|&&die| die <= HIGHEST_RETURNING_FACE).count(),
+        outcome,
+    })
+}
+
+fn roll_on_table(rng: &mut impl Rng, subclass: &str, kind: &str, table: &[String]) -> anyhow::Result<String> {
+    table
+        .choose(rng)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("{} has no {} table", subclass, kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded_rng;
+
+    fn necromancer() -> Subclass {
+        Subclass {
+            mishaps: vec!["Your hair turns white".to_string()],
+            dooms: vec!["Death comes for you".to_string()],
+            ..Subclass::from("Necromancer".to_string())
+        }
+    }
+
+    #[test]
+    fn test_outcomes() {
+        let mut rng = seeded_rng(0);
+        let subclass = necromancer();
+
+        let casting = resolve_magic_dice(&mut rng, &subclass, &[1, 4, 6]).unwrap();
+        assert_eq!(casting.outcome, CastOutcome::Normal);
+        assert_eq!((casting.sum, casting.returned), (11, 1));
+
+        let casting = resolve_magic_dice(&mut rng, &subclass, &[2, 5, 2]).unwrap();
+        assert_eq!(casting.outcome, CastOutcome::Mishap("Your hair turns white".to_string()));
+
+        let casting = resolve_magic_dice(&mut rng, &subclass, &[3, 3, 3, 1]).unwrap();
+        assert_eq!(casting.outcome, CastOutcome::Doom("Death comes for you".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_dice_and_missing_tables() {
+        let mut rng = seeded_rng(0);
+
+        assert!(resolve_magic_dice(&mut rng, &necromancer(), &[0, 4]).is_err());
+        assert!(resolve_magic_dice(&mut rng, &necromancer(), &[7]).is_err());
+
+        let plain = Subclass::from("Orthodox".to_string());
+        assert!(resolve_magic_dice(&mut rng, &plain, &[1, 2, 3]).is_ok());
+        let err = resolve_magic_dice(&mut rng, &plain, &[2, 2]).unwrap_err();
+        assert_eq!(err.to_string(), "Orthodox has no mishap table");
+    }
+
+    #[test]
+    fn test_roll_magic_dice() {
+        let dice = roll_magic_dice(&mut seeded_rng(4), 20);
+        assert_eq!(dice.len(), 20);
+        assert!(dice.iter().all(|die| (1..=MAGIC_DIE_FACES).contains(die)));
+    }
+}
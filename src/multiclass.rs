@@ -0,0 +1,252 @@
+use crate::{choose_allowed, CharacterClass, CharacterGenerator, Class, Constraints, Subclass, MAX_TEMPLATES};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+
+// How often a character takes their next template from a new class instead
+// of one they already have. Off unless the config or caller sets a chance.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct MulticlassPolicy {
+    // Chance of branching out at each template after the first
+    pub chance: f64,
+    // Most classes one character can hold
+    pub max_classes: u8,
+}
+
+impl Default for MulticlassPolicy {
+    fn default() -> Self {
+        Self {
+            chance: 0.0,
+            max_classes: 2,
+        }
+    }
+}
+
+impl MulticlassPolicy {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&self.chance) {
+            return Err(anyhow::anyhow!("chance must be between 0 and 1, not {}", self.chance));
+        }
+        if self.max_classes < 1 {
+            return Err(anyhow::anyhow!("max_classes must be at least 1"));
+        }
+
+        Ok(())
+    }
+}
+
+impl CharacterGenerator {
+    // Use this multiclass policy instead of the config's
+    pub fn with_multiclass(mut self, policy: MulticlassPolicy) -> anyhow::Result<Self> {
+        policy.validate().map_err(|e| anyhow::anyhow!("Invalid multiclass policy: {}", e))?;
+
+        self.config.multiclass = policy;
+        Ok(self)
+    }
+
+    // Template A of a class, with a subclass picked if it has any
+    pub(crate) fn start_class(&self, rng: &mut impl Rng, class: &Class, constraints: &Constraints) -> anyhow::Result<CharacterClass> {
+        let mut subclass = None;
+        if !class.subclasses.is_empty() {
+            let chosen = choose_allowed(
+                rng,
+                &format!("{} subclass", class.name),
+                &class.subclasses,
+                |subclass| constraints.allows_subclass(subclass),
+                |subclass| self.config.subclass_weight(self.setting.as_deref(), class, subclass),
+            )?;
+            subclass = Some(chosen.name.clone());
+        }
+
+        Ok(CharacterClass {
+            name: class.name.clone(),
+            subclass,
+            templates: 1,
+        })
+    }
+
+    // Give the character their next template, either in a new class (by the
+    // multiclass policy) or in one they already have. Returns the index of
+    // the class that took it.
+    pub(crate) fn take_template(
+        &self,
+        rng: &mut impl Rng,
+        classes: &mut Vec<CharacterClass>,
+        constraints: &Constraints,
+    ) -> anyhow::Result<usize> {
+        let policy = &self.config.multiclass;
+        // No roll is made while branching is off, so single-class characters
+        // come out the same for a given seed whatever the policy's limits
+        if policy.chance > 0.0 && classes.len() < usize::from(policy.max_classes) && rng.gen_bool(policy.chance) {
+            if let Some(class) = self.branch_class(rng, classes, constraints) {
+                classes.push(self.start_class(rng, class, constraints)?);
+                return Ok(classes.len() - 1);
+            }
+        }
+
+        let open: Vec<usize> = (0..classes.len())
+            .filter(|&index| classes[index].templates < MAX_TEMPLATES)
+            .collect();
+        let index = match open[..] {
+            [] => return Err(anyhow::anyhow!("Every class already has all {} templates", MAX_TEMPLATES)),
+            [index] => index,
+            _ => *open.choose(rng).expect("open has several classes"),
+        };
+        classes[index].templates += 1;
+
+        Ok(index)
+    }
+
+    // A weighted pick of the allowed classes the character doesn't have yet
+    fn branch_class(&self, rng: &mut impl Rng, classes: &[CharacterClass], constraints: &Constraints) -> Option<&Class> {
+        let setting = self.setting.as_deref();
+        choose_allowed(
+            rng,
+            "class",
+            &self.config.classes,
+            |class| constraints.allows_class(class) && !classes.iter().any(|taken| taken.name == class.name),
+            |class| self.config.class_weight(setting, class),
+        )
+        .ok()
+    }
+
+    // The config's record for a class the character has taken
+    pub(crate) fn subclass_record(&self, class: &CharacterClass) -> Option<&Subclass> {
+        let subclass = class.subclass.as_ref()?;
+        self.config.class(&class.name)?.subclasses.iter().find(|record| &record.name == subclass)
+    }
+
+    // Magic comes from the first of the character's classes whose subclass
+    // has a spell list
+    pub(crate) fn casting_class<'a>(&'a self, classes: &'a [CharacterClass]) -> Option<(&'a CharacterClass, &'a Subclass)> {
+        classes.iter().find_map(|class| {
+            self.subclass_record(class)
+                .filter(|subclass| !subclass.spells.is_empty())
+                .map(|subclass| (class, subclass))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::RollSpec;
+    use crate::{seeded_rng, Config};
+
+    fn generator(chance: f64, max_classes: u8) -> CharacterGenerator {
+        let config: Config = toml::from_str(
+            r#"
+            species = ["Human"]
+            classes = [
+                "Fighter",
+                "Thief",
+                { name = "Wizard", subclasses = [{ name = "Necromancer", spells = ["Raise Corpse", "Bone Spear", "Wither"] }] },
+            ]
+
+            [hit_points]
+            template_bonus = { Fighter = 1 }
+
+            [class_templates.Fighter]
+            A = [{ name = "Parry", text = "Reduce damage" }]
+            B = [{ name = "Cleave", text = "Attack again" }]
+
+            [class_templates.Thief]
+            A = [{ name = "Sneak Attack", text = "Extra damage" }]
+            "#,
+        )
+        .unwrap();
+        CharacterGenerator::from_config(config)
+            .with_multiclass(MulticlassPolicy { chance, max_classes })
+            .unwrap()
+    }
+
+    fn generate(generator: &CharacterGenerator, seed: u64, level: u8) -> crate::Character {
+        generator
+            .generate_character(&mut seeded_rng(seed), level, &RollSpec::default(), &Constraints::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_no_branching_by_default() {
+        let generator = generator(0.0, 3);
+        for seed in 0..20 {
+            let character = generate(&generator, seed, 4);
+            assert_eq!(character.classes.len(), 1);
+            assert_eq!(character.classes[0].templates, 4);
+        }
+    }
+
+    #[test]
+    fn test_branching_follows_policy() {
+        let two_classes = generator(1.0, 2);
+        for seed in 0..20 {
+            let character = generate(&two_classes, seed, 4);
+            assert_eq!(character.classes.len(), 2);
+            assert_eq!(character.templates().len(), 4);
+            assert_ne!(character.classes[0].name, character.classes[1].name);
+
+            // Features follow the templates taken in each class
+            for class in &character.classes {
+                let features = character.features.iter().filter(|feature| feature.class == class.name).count();
+                assert_eq!(features, two_classes.class_features(&class.name, class.templates).len());
+            }
+
+            // Only the Wizard's templates count towards Magic Dice
+            let wizard = character.classes.iter().find(|class| class.name == "Wizard");
+            assert_eq!(character.magic.as_ref().map(|magic| magic.magic_dice), wizard.map(|class| class.templates));
+        }
+
+        let character = generate(&generator(1.0, 3), 1, 3);
+        assert_eq!(character.class_name().matches(" / ").count(), 2);
+        assert!(character.classes.iter().all(|class| class.templates == 1));
+    }
+
+    #[test]
+    fn test_branching_respects_constraints() {
+        let generator = generator(1.0, 3);
+        let constraints = Constraints {
+            exclude_classes: vec!["Thief".to_string()],
+            ..Constraints::default()
+        };
+        for seed in 0..10 {
+            let character = generator
+                .generate_character(&mut seeded_rng(seed), 4, &RollSpec::default(), &constraints)
+                .unwrap();
+            assert!(character.classes.iter().all(|class| class.name != "Thief"));
+            assert_eq!(character.classes.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_class_names_and_templates() {
+        let character = crate::Character {
+            classes: vec![
+                CharacterClass {
+                    name: "Fighter".to_string(),
+                    subclass: None,
+                    templates: 2,
+                },
+                CharacterClass {
+                    name: "Wizard".to_string(),
+                    subclass: Some("Necromancer".to_string()),
+                    templates: 1,
+                },
+            ],
+            ..generate(&generator(0.0, 2), 0, 1)
+        };
+        assert_eq!(character.class_name(), "Fighter / Wizard (Necromancer)");
+        assert_eq!(character.templates(), ["Fighter A", "Fighter B", "Wizard A"]);
+    }
+
+    #[test]
+    fn test_invalid_policy() {
+        let config = generator(0.0, 2).get_config().clone();
+        let policy = MulticlassPolicy {
+            chance: 1.5,
+            ..MulticlassPolicy::default()
+        };
+        let err = CharacterGenerator::from_config(config).with_multiclass(policy).err().unwrap();
+        assert_eq!(err.to_string(), "Invalid multiclass policy: chance must be between 0 and 1, not 1.5");
+    }
+}
@@ -0,0 +1,188 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Markov names are built from pairs of letters, and give up on a word that
+// runs too long
+const MARKOV_ORDER: usize = 2;
+const MIN_NAME_LENGTH: usize = 3;
+const MAX_NAME_LENGTH: usize = 12;
+const MARKOV_ATTEMPTS: usize = 20;
+
+// How a species names its people. Use one of `given` (a plain list),
+// `syllables` (one pick from each group, joined) or `markov` (example names
+// to learn letter patterns from). `surnames` are added to any of them.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct NameRules {
+    pub given: Vec<String>,
+    pub syllables: Vec<Vec<String>>,
+    pub markov: Vec<String>,
+    pub surnames: Vec<String>,
+}
+
+impl NameRules {
+    pub fn is_empty(&self) -> bool {
+        self.given.is_empty() && self.syllables.is_empty() && self.markov.is_empty()
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let styles = [!self.given.is_empty(), !self.syllables.is_empty(), !self.markov.is_empty()];
+        if styles.iter().filter(|&&used| used).count() > 1 {
+            return Err(anyhow::anyhow!("only one of given, syllables and markov can be used"));
+        }
+        if self.syllables.iter().any(|group| group.is_empty()) {
+            return Err(anyhow::anyhow!("syllable groups cannot be empty"));
+        }
+        if self.markov.iter().any(|seed| seed.trim().is_empty()) {
+            return Err(anyhow::anyhow!("markov example names cannot be empty"));
+        }
+        if self.is_empty() && !self.surnames.is_empty() {
+            return Err(anyhow::anyhow!("surnames need given, syllables or markov names to go with them"));
+        }
+
+        Ok(())
+    }
+
+    pub fn generate(&self, rng: &mut impl Rng) -> Option<String> {
+        let name = if !self.given.is_empty() {
+            self.given.choose(rng).cloned()?
+        } else if !self.syllables.is_empty() {
+            self.syllables
+                .iter()
+                .filter_map(|group| group.choose(rng))
+                .map(String::as_str)
+                .collect()
+        } else if !self.markov.is_empty() {
+            markov_name(rng, &self.markov)
+        } else {
+            return None;
+        };
+
+        Some(match self.surnames.choose(rng) {
+            Some(surname) => format!("{} {}", name, surname),
+            None => name,
+        })
+    }
+}
+
+// A new name that follows the letter patterns of the examples. Too-short
+// results are rerolled a few times before being accepted anyway.
+fn markov_name(rng: &mut impl Rng, seeds: &[String]) -> String {
+    // Map each MARKOV_ORDER-letter prefix to the letters that follow it,
+    // with None marking the end of a name
+    let mut chain: HashMap<Vec<char>, Vec<Option<char>>> = HashMap::new();
+    for seed in seeds {
+        let letters: Vec<char> = seed.trim().to_lowercase().chars().collect();
+        let mut prefix = vec!['^'; MARKOV_ORDER];
+        for &letter in letters.iter().chain(std::iter::once(&'$')) {
+            let next = (letter != '$').then_some(letter);
+            chain.entry(prefix.clone()).or_default().push(next);
+            prefix.remove(0);
+            prefix.push(letter);
+        }
+    }
+
+    let mut name = String::new();
+    for _ in 0..MARKOV_ATTEMPTS {
+        name.clear();
+        let mut prefix = vec!['^'; MARKOV_ORDER];
+        while name.chars().count() < MAX_NAME_LENGTH {
+            let Some(&Some(letter)) = chain.get(&prefix).and_then(|next| next.choose(rng)) else {
+                break;
+            };
+            name.push(letter);
+            prefix.remove(0);
+            prefix.push(letter);
+        }
+        if name.chars().count() >= MIN_NAME_LENGTH {
+            break;
+        }
+    }
+
+    capitalize(&name)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut letters = word.chars();
+    match letters.next() {
+        Some(first) => first.to_uppercase().chain(letters).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded_rng;
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_given_names_and_surnames() {
+        let rules = NameRules {
+            given: strings(&["Whisker", "Nibs"]),
+            surnames: strings(&["Gutterson"]),
+            ..NameRules::default()
+        };
+        let name = rules.generate(&mut seeded_rng(1)).unwrap();
+        assert!(name == "Whisker Gutterson" || name == "Nibs Gutterson");
+        assert_eq!(NameRules::default().generate(&mut seeded_rng(1)), None);
+    }
+
+    #[test]
+    fn test_syllable_names() {
+        let rules = NameRules {
+            syllables: vec![strings(&["Skr", "Sn"]), strings(&["ee"]), strings(&["k"])],
+            ..NameRules::default()
+        };
+        for seed in 0..10 {
+            let name = rules.generate(&mut seeded_rng(seed)).unwrap();
+            assert!(name == "Skreek" || name == "Sneek", "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_markov_names() {
+        let rules = NameRules {
+            markov: strings(&["Aelar", "Aerendil", "Thalion", "Elaria", "Lirael"]),
+            ..NameRules::default()
+        };
+        for seed in 0..20 {
+            let name = rules.generate(&mut seeded_rng(seed)).unwrap();
+            let length = name.chars().count();
+            assert!((MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&length), "{}", name);
+            assert!(name.chars().next().unwrap().is_uppercase());
+            assert!(name.chars().skip(1).all(|letter| "aelrndithoi".contains(letter)), "{}", name);
+        }
+
+        // The same seed always gives the same name
+        assert_eq!(rules.generate(&mut seeded_rng(3)), rules.generate(&mut seeded_rng(3)));
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let mixed = NameRules {
+            given: strings(&["Bob"]),
+            markov: strings(&["Robert"]),
+            ..NameRules::default()
+        };
+        assert!(mixed.validate().is_err());
+
+        let empty_group = NameRules {
+            syllables: vec![strings(&["Gr"]), Vec::new()],
+            ..NameRules::default()
+        };
+        assert!(empty_group.validate().is_err());
+
+        let surnames_only = NameRules {
+            surnames: strings(&["Smith"]),
+            ..NameRules::default()
+        };
+        assert!(surnames_only.validate().is_err());
+        assert!(NameRules::default().validate().is_ok());
+    }
+}
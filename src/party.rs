@@ -0,0 +1,376 @@
+use crate::dice::RollSpec;
+use crate::levels::LevelSpec;
+use crate::{choose_allowed, Character, CharacterGenerator, Class, Constraints};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Classes and species are picked to fit the rules member by member; only the
+// ability total band needs whole parties to be rerolled, this many times at most
+pub const PARTY_ATTEMPTS: usize = 1000;
+
+// Composition rules for a party. Every rule is off by default.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PartyRules {
+    // No two members share a class, counting classes branched into
+    pub unique_classes: bool,
+    // One member of each listed class; list a class twice to require two
+    pub required_classes: Vec<String>,
+    // Most members allowed to share a species
+    pub max_per_species: Option<u8>,
+    // Band for the sum of every member's ability scores
+    pub min_ability_total: Option<u32>,
+    pub max_ability_total: Option<u32>,
+}
+
+impl PartyRules {
+    pub fn allows_ability_total(&self, total: u32) -> bool {
+        self.min_ability_total.is_none_or(|min| total >= min) && self.max_ability_total.is_none_or(|max| total <= max)
+    }
+
+    fn describe_ability_band(&self) -> String {
+        match (self.min_ability_total, self.max_ability_total) {
+            (Some(min), Some(max)) => format!("between {} and {}", min, max),
+            (Some(min), None) => format!("of at least {}", min),
+            (None, Some(max)) => format!("of at most {}", max),
+            (None, None) => "of any size".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Party {
+    pub members: Vec<Character>,
+}
+
+impl Party {
+    pub fn ability_total(&self) -> u32 {
+        self.members.iter().map(|member| member.ability_scores.total()).sum()
+    }
+}
+
+impl CharacterGenerator {
+    pub fn generate_party(
+        &self,
+        rng: &mut impl Rng,
+        levels: &LevelSpec,
+        size: u8,
+        method: &RollSpec,
+        constraints: &Constraints,
+        rules: &PartyRules,
+    ) -> anyhow::Result<Party> {
+        self.validate_party_rules(size, constraints, rules)?;
+
+        for _ in 0..PARTY_ATTEMPTS {
+            let party = self.roll_party(rng, levels, size, method, constraints, rules)?;
+            if rules.allows_ability_total(party.ability_total()) {
+                return Ok(party);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Could not roll a party with an ability total {} in {} attempts",
+            rules.describe_ability_band(),
+            PARTY_ATTEMPTS
+        ))
+    }
+
+    fn roll_party(
+        &self,
+        rng: &mut impl Rng,
+        levels: &LevelSpec,
+        size: u8,
+        method: &RollSpec,
+        constraints: &Constraints,
+        rules: &PartyRules,
+    ) -> anyhow::Result<Party> {
+        let setting = self.setting.as_deref();
+
+        // Starting classes are settled first, required classes taking the
+        // first places, so that members who branch out into a new class can
+        // never take one that someone else starts in
+        let mut starting: Vec<&Class> = Vec::new();
+        for index in 0..usize::from(size) {
+            let class = match rules.required_classes.get(index) {
+                Some(name) => self.config.class(name).expect("required classes are checked before rolling"),
+                None => choose_allowed(
+                    rng,
+                    "class",
+                    &self.config.classes,
+                    |class| {
+                        constraints.allows_class(class)
+                            && !(rules.unique_classes && starting.iter().any(|taken| taken.name == class.name))
+                    },
+                    |class| self.config.class_weight(setting, class),
+                )?,
+            };
+            starting.push(class);
+        }
+
+        let mut members: Vec<Character> = Vec::new();
+        let mut species_counts: HashMap<String, u8> = HashMap::new();
+        for class in &starting {
+            let mut member = constraints.clone();
+            if rules.unique_classes {
+                member.exclude_classes.extend(starting.iter().map(|class| class.name.clone()));
+                member
+                    .exclude_classes
+                    .extend(members.iter().flat_map(|member| &member.classes).map(|class| class.name.clone()));
+            }
+            if let Some(max) = rules.max_per_species {
+                member
+                    .exclude_species
+                    .extend(species_counts.iter().filter(|(_, &count)| count >= max).map(|(species, _)| species.clone()));
+            }
+
+            let level = levels.roll(rng);
+            let character = self.build_character(rng, level, method, &member, Some(class))?;
+            *species_counts.entry(character.species.clone()).or_default() += 1;
+            members.push(character);
+        }
+
+        members.shuffle(rng);
+        Ok(Party { members })
+    }
+
+    // Catch rules that no amount of rerolling could satisfy
+    fn validate_party_rules(&self, size: u8, constraints: &Constraints, rules: &PartyRules) -> anyhow::Result<()> {
+        if size < 1 {
+            return Err(anyhow::anyhow!("A party needs at least 1 member"));
+        }
+        if size > 100 {
+            return Err(anyhow::anyhow!("Cannot generate a party of more than 100 members"));
+        }
+        self.validate_constraints(constraints)?;
+
+        let setting = self.setting.as_deref();
+        if rules.required_classes.len() > usize::from(size) {
+            return Err(anyhow::anyhow!(
+                "A party of {} cannot include {} required classes",
+                size,
+                rules.required_classes.len()
+            ));
+        }
+        for (index, class) in rules.required_classes.iter().enumerate() {
+            let Some(record) = self.config.class(class) else {
+                return Err(anyhow::anyhow!("Unknown class: {}", class));
+            };
+            if !constraints.allows_class(record) || self.config.class_weight(setting, record) <= 0.0 {
+                return Err(anyhow::anyhow!("Required class {} is ruled out", class));
+            }
+            if rules.unique_classes && rules.required_classes[..index].contains(class) {
+                return Err(anyhow::anyhow!("Required class {} is listed twice but classes must be unique", class));
+            }
+        }
+
+        if rules.unique_classes {
+            let available = self
+                .config
+                .classes
+                .iter()
+                .filter(|class| constraints.allows_class(class) && self.config.class_weight(setting, class) > 0.0)
+                .count();
+            if available < usize::from(size) {
+                return Err(anyhow::anyhow!(
+                    "Only {} classes are available for a party of {} with no repeated classes",
+                    available,
+                    size
+                ));
+            }
+        }
+
+        if let Some(max) = rules.max_per_species {
+            let available = self
+                .config
+                .species
+                .iter()
+                .filter(|species| constraints.allows_species(species) && self.config.species_weight(setting, species) > 0.0)
+                .count();
+            if available * usize::from(max) < usize::from(size) {
+                return Err(anyhow::anyhow!(
+                    "Only {} species are available at {} each for a party of {}",
+                    available,
+                    max,
+                    size
+                ));
+            }
+        }
+
+        if let (Some(min), Some(max)) = (rules.min_ability_total, rules.max_ability_total) {
+            if min > max {
+                return Err(anyhow::anyhow!("Minimum ability total {} is above the maximum {}", min, max));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{seeded_rng, Class, Config, Species};
+
+    fn generator() -> CharacterGenerator {
+        let config: Config = toml::from_str(
+            r#"
+            species = ["Human", "Elf", "Goblin"]
+            classes = ["Fighter", "Thief", "Wizard", "Knight"]
+            "#,
+        )
+        .unwrap();
+        CharacterGenerator::from_config(config)
+    }
+
+    fn base_class(character: &Character) -> &str {
+        &character.classes[0].name
+    }
+
+    #[test]
+    fn test_party_rules_are_met() {
+        let generator = generator();
+        let rules = PartyRules {
+            unique_classes: true,
+            required_classes: vec!["Wizard".to_string()],
+            max_per_species: Some(2),
+            ..PartyRules::default()
+        };
+
+        for seed in 0..20 {
+            let party = generator
+                .generate_party(&mut seeded_rng(seed), &LevelSpec::from(1), 4, &RollSpec::default(), &Constraints::default(), &rules)
+                .unwrap();
+            assert_eq!(party.members.len(), 4);
+
+            let mut classes: Vec<&str> = party.members.iter().map(base_class).collect();
+            assert!(classes.contains(&"Wizard"));
+            classes.sort();
+            classes.dedup();
+            assert_eq!(classes.len(), 4);
+
+            for species in ["Human", "Elf", "Goblin"] {
+                assert!(party.members.iter().filter(|member| member.species == species).count() <= 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unique_classes_with_multiclassing() {
+        let generator = generator()
+            .with_multiclass(crate::multiclass::MulticlassPolicy {
+                chance: 1.0,
+                max_classes: 2,
+            })
+            .unwrap();
+        let rules = PartyRules {
+            unique_classes: true,
+            ..PartyRules::default()
+        };
+
+        for seed in 0..20 {
+            let party = generator
+                .generate_party(&mut seeded_rng(seed), &LevelSpec::from(2), 2, &RollSpec::default(), &Constraints::default(), &rules)
+                .unwrap();
+            let mut classes: Vec<&str> = party.members.iter().flat_map(|member| &member.classes).map(|class| class.name.as_str()).collect();
+            assert_eq!(classes.len(), 4);
+            classes.sort();
+            classes.dedup();
+            assert_eq!(classes.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_ability_total_band() {
+        let generator = generator();
+        let rules = PartyRules {
+            min_ability_total: Some(200),
+            max_ability_total: Some(210),
+            ..PartyRules::default()
+        };
+        let party = generator
+            .generate_party(&mut seeded_rng(1), &LevelSpec::from(1), 3, &RollSpec::default(), &Constraints::default(), &rules)
+            .unwrap();
+        assert!((200..=210).contains(&party.ability_total()));
+
+        // 3d6 can never total more than 18 * 6 per member
+        let rules = PartyRules {
+            min_ability_total: Some(1000),
+            ..PartyRules::default()
+        };
+        let err = generator
+            .generate_party(&mut seeded_rng(1), &LevelSpec::from(1), 3, &RollSpec::default(), &Constraints::default(), &rules)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Could not roll a party with an ability total of at least 1000 in 1000 attempts");
+    }
+
+    #[test]
+    fn test_impossible_rules() {
+        let generator = generator();
+        let party = |size, constraints: &Constraints, rules: &PartyRules| {
+            generator.generate_party(&mut seeded_rng(0), &LevelSpec::from(1), size, &RollSpec::default(), constraints, rules)
+        };
+        let unique = PartyRules {
+            unique_classes: true,
+            ..PartyRules::default()
+        };
+
+        assert!(party(5, &Constraints::default(), &unique).is_err());
+        assert!(party(4, &Constraints::default(), &unique).is_ok());
+
+        let no_thieves = Constraints {
+            exclude_classes: vec!["Thief".to_string()],
+            ..Constraints::default()
+        };
+        let err = party(4, &no_thieves, &unique).unwrap_err();
+        assert_eq!(err.to_string(), "Only 3 classes are available for a party of 4 with no repeated classes");
+
+        let thief = PartyRules {
+            required_classes: vec!["Thief".to_string()],
+            ..PartyRules::default()
+        };
+        assert!(party(2, &no_thieves, &thief).is_err());
+
+        let two_wizards = PartyRules {
+            unique_classes: true,
+            required_classes: vec!["Wizard".to_string(), "Wizard".to_string()],
+            ..PartyRules::default()
+        };
+        assert!(party(2, &Constraints::default(), &two_wizards).is_err());
+
+        let one_each = PartyRules {
+            max_per_species: Some(1),
+            ..PartyRules::default()
+        };
+        assert!(party(4, &Constraints::default(), &one_each).is_err());
+
+        let backwards = PartyRules {
+            min_ability_total: Some(100),
+            max_ability_total: Some(50),
+            ..PartyRules::default()
+        };
+        assert!(party(2, &Constraints::default(), &backwards).is_err());
+    }
+
+    #[test]
+    fn test_zero_weight_classes_are_not_available() {
+        let mut config = generator().get_config().clone();
+        config.classes = vec![
+            Class::from("Fighter".to_string()),
+            Class {
+                weight: 0.0,
+                ..Class::from("Wizard".to_string())
+            },
+        ];
+        config.species = vec![Species::from("Human".to_string())];
+        let generator = CharacterGenerator::from_config(config);
+        let rules = PartyRules {
+            unique_classes: true,
+            ..PartyRules::default()
+        };
+
+        let result = generator.generate_party(&mut seeded_rng(0), &LevelSpec::from(1), 2, &RollSpec::default(), &Constraints::default(), &rules);
+        assert!(result.is_err());
+    }
+}
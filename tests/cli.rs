@@ -0,0 +1,280 @@
+mod common;
+
+use common::{config_path, run, stderr, stdout, temp_dir};
+use std::fs;
+
+const CLI: &str = env!("CARGO_BIN_EXE_cli");
+
+#[test]
+fn test_cli_generates_and_saves_characters() {
+    let dir = temp_dir("cli-save");
+    let config = config_path();
+    let output = run(CLI, &dir, &["--config", config.to_str().unwrap(), "--level", "2", "--count", "3", "--seed", "42"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Seed: 42"));
+    assert!(stdout.contains("Character 3:"));
+    assert!(stdout.contains("Characters saved to: characters_level_2_count_3.toml"));
+
+    let saved = fs::read_to_string(dir.join("characters_level_2_count_3.toml")).unwrap();
+    assert!(saved.starts_with("seed = 42"));
+    assert_eq!(saved.matches("[[characters]]").count(), 3);
+    assert_eq!(stdout.matches("Inventory (").count(), 3);
+    assert_eq!(stdout.matches("Quirk: ").count(), 3);
+    assert!(saved.contains("[[characters.inventory.pack]]"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_same_seed_same_output() {
+    let dir = temp_dir("cli-seed");
+    let config = config_path();
+    let args = ["--config", config.to_str().unwrap(), "--count", "5", "--method", "4d6kh3", "--seed", "7"];
+
+    let first = run(CLI, &dir, &args);
+    let first_file = fs::read_to_string(dir.join("characters_level_1_count_5.toml")).unwrap();
+    let second = run(CLI, &dir, &args);
+    let second_file = fs::read_to_string(dir.join("characters_level_1_count_5.toml")).unwrap();
+
+    assert_eq!(stdout(&first), stdout(&second));
+    assert_eq!(first_file, second_file);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_level_range() {
+    let dir = temp_dir("cli-levels");
+    let config = config_path();
+    let output = run(CLI, &dir, &["--config", config.to_str().unwrap(), "--level", "2:1,4:1", "--count", "10", "--seed", "3"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    let mut levels: Vec<&str> = stdout.lines().filter_map(|line| line.strip_prefix("Level: ")).collect();
+    levels.sort();
+    levels.dedup();
+    assert_eq!(levels, ["2", "4"]);
+    assert!(dir.join("characters_level_2-4_count_10.toml").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_party() {
+    let dir = temp_dir("cli-party");
+    let config = config_path();
+    let output = run(CLI, &dir, &[
+        "--config", config.to_str().unwrap(),
+        "--party", "--count", "5", "--seed", "12",
+        "--unique-classes", "--require-class", "Wizard", "--require-class", "Knight",
+    ]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    let mut classes: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("Class: "))
+        .map(|class| class.split(" (").next().unwrap())
+        .collect();
+    assert!(classes.contains(&"Wizard") && classes.contains(&"Knight"));
+    classes.sort();
+    classes.dedup();
+    assert_eq!(classes.len(), 5);
+    assert!(stdout.contains("Party ability total: "));
+
+    // Party rules only make sense with --party
+    let output = run(CLI, &dir, &["--config", config.to_str().unwrap(), "--unique-classes"]);
+    assert!(!output.status.success());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_name_override() {
+    let dir = temp_dir("cli-names");
+    let config = config_path();
+    let output = run(CLI, &dir, &["--config", config.to_str().unwrap(), "--count", "3", "--name", "Alys", "--name", "Bram"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    let names: Vec<&str> = stdout.lines().filter_map(|line| line.strip_prefix("Name: ")).collect();
+    assert_eq!(names.len(), 3);
+    assert_eq!(names[..2], ["Alys", "Bram"]);
+
+    let saved = fs::read_to_string(dir.join("characters_level_1_count_3.toml")).unwrap();
+    assert!(saved.contains("name = \"Alys\""));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_level_up() {
+    let dir = temp_dir("cli-level-up");
+    let config = config_path();
+    let config = config.to_str().unwrap();
+    let output = run(CLI, &dir, &["--config", config, "--level", "3", "--count", "2", "--seed", "9"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let output = run(CLI, &dir, &["level-up", "characters_level_3_count_2.toml", "--character", "2", "--config", config, "--seed", "1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let sheets = stdout(&output);
+    assert!(sheets.contains("Character 2:"));
+    assert!(!sheets.contains("Character 1:"));
+    assert!(sheets.contains("Gained at level 4:"));
+
+    // Only the chosen character is levelled, in place, and the level-up's
+    // seed is kept alongside the one that generated the characters
+    let saved = fs::read_to_string(dir.join("characters_level_3_count_2.toml")).unwrap();
+    assert!(saved.starts_with("seed = 9"));
+    assert!(saved.contains("[[level_ups]]\nseed = 1\ncharacter = 2\n"), "{}", saved);
+    assert_eq!(saved.matches("level = 3").count(), 1);
+    assert_eq!(saved.matches("level = 4").count(), 1);
+
+    let output = run(CLI, &dir, &["level-up", "characters_level_3_count_2.toml", "--config", config, "--seed", "4"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let saved = fs::read_to_string(dir.join("characters_level_3_count_2.toml")).unwrap();
+    assert!(saved.contains("[[level_ups]]\nseed = 1\ncharacter = 2\n\n[[level_ups]]\nseed = 4\n"), "{}", saved);
+
+    let output = run(CLI, &dir, &["level-up", "characters_level_3_count_2.toml", "--character", "3", "--config", config]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("only has 2 character(s)"));
+
+    // A character already at the top stops the whole run before anything
+    // is shown or saved
+    let output = run(CLI, &dir, &["--config", config, "--level", "9", "--count", "2", "--seed", "3"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = run(CLI, &dir, &["level-up", "characters_level_9_count_2.toml", "--character", "1", "--config", config]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let before = fs::read_to_string(dir.join("characters_level_9_count_2.toml")).unwrap();
+    let output = run(CLI, &dir, &["level-up", "characters_level_9_count_2.toml", "--config", config]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Character 1: Character is already at the highest level, 10"));
+    assert!(!stdout(&output).contains("Gained at level"));
+    assert_eq!(fs::read_to_string(dir.join("characters_level_9_count_2.toml")).unwrap(), before);
+
+    let output = run(CLI, &dir, &["level-up", "missing.toml", "--config", config]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Could not read characters file: missing.toml"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_multiclass() {
+    let dir = temp_dir("cli-multiclass");
+    let config = config_path();
+    let config = config.to_str().unwrap();
+    let output = run(CLI, &dir, &["--config", config, "--level", "4", "--count", "5", "--seed", "2", "--multiclass-chance", "1"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    let classes: Vec<&str> = stdout.lines().filter_map(|line| line.strip_prefix("Class: ")).collect();
+    assert_eq!(classes.len(), 5);
+    assert!(classes.iter().all(|class| class.matches(" / ").count() == 1), "{:?}", classes);
+    for templates in stdout.lines().filter_map(|line| line.strip_prefix("Templates: ")) {
+        assert_eq!(templates.split(", ").count(), 4);
+    }
+
+    let output = run(CLI, &dir, &["--config", config, "--multiclass-chance", "2"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("chance must be between 0 and 1"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_ability_method() {
+    let dir = temp_dir("cli-ability-method");
+    let config = config_path();
+    let config = config.to_str().unwrap();
+    let args = ["--config", config, "--species", "Human", "--class", "Wizard", "--count", "3", "--seed", "4"];
+    let output = run(CLI, &dir, &[&args[..], &["--ability-method", "standard-array"]].concat());
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let sheets = stdout(&output);
+    assert!(sheets.contains("Method: standard-array"));
+    // Humans have no ability adjustments, so the array comes through as is,
+    // best first for a Wizard
+    assert_eq!(sheets.matches("Intelligence: 15").count(), 3);
+    assert_eq!(sheets.matches("Wisdom: 13").count(), 3);
+
+    let output = run(CLI, &dir, &[&args[..], &["--ability-method", "arrange", "--method", "4d6kh3"]].concat());
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Method: 4d6kh3 (arrange)"));
+
+    let output = run(CLI, &dir, &["--config", config, "--ability-method", "best"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("unknown ability method 'best'"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_funnel() {
+    let dir = temp_dir("cli-funnel");
+    let config = config_path();
+    let config = config.to_str().unwrap();
+    let output = run(CLI, &dir, &["--config", config, "--funnel", "--players", "3", "--count", "4", "--seed", "6"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let funnel = stdout(&output);
+    for player in 1..=3 {
+        assert!(funnel.contains(&format!("=== Player {} ===", player)));
+    }
+    assert_eq!(funnel.matches("Level: 0").count(), 12);
+    assert_eq!(funnel.matches("Occupation: ").count(), 12);
+    assert!(!funnel.contains("Class: "));
+
+    let saved = fs::read_to_string(dir.join("funnel_players_3_count_12.toml")).unwrap();
+    assert!(saved.contains("players = [\n    1,\n    1,\n    1,\n    1,\n    2,"), "{}", saved);
+    assert_eq!(saved.matches("[[characters]]").count(), 12);
+
+    // Survivors level up into their first class, keeping their players
+    let output = run(CLI, &dir, &["level-up", "funnel_players_3_count_12.toml", "--config", config, "--seed", "2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let sheets = stdout(&output);
+    assert_eq!(sheets.matches("Gained at level 1:").count(), 12);
+    assert_eq!(sheets.matches("Templates: ").count(), 12);
+    let levelled = fs::read_to_string(dir.join("funnel_players_3_count_12.toml")).unwrap();
+    assert_eq!(levelled.matches("level = 1").count(), 12);
+    assert!(levelled.contains("[[level_ups]]\nseed = 2\n"));
+    assert!(levelled.contains("players = [\n    1,\n    1,\n    1,\n    1,\n    2,"));
+
+    // A funnel is always level 0 and classless
+    let output = run(CLI, &dir, &["--config", config, "--funnel", "--class", "Fighter"]);
+    assert!(!output.status.success());
+    let output = run(CLI, &dir, &["--config", config, "--players", "2"]);
+    assert!(!output.status.success());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_rejects_bad_input() {
+    let dir = temp_dir("cli-errors");
+    let config = config_path();
+    let config = config.to_str().unwrap();
+
+    let output = run(CLI, &dir, &["--config", config, "--method", "3d0"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("at least one face"));
+
+    let output = run(CLI, &dir, &["--config", config, "--level", "11"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("level 11 is outside 1 to 10"));
+
+    let output = run(CLI, &dir, &["--config", config, "--name", "Alys", "--name", "Bram"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Got 2 names for only 1 character(s)"));
+
+    let output = run(CLI, &dir, &["--config", "missing.toml"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Could not read config file"));
+
+    // Nothing is written when generation fails
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    fs::remove_dir_all(dir).unwrap();
+}
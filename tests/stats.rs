@@ -0,0 +1,88 @@
+mod common;
+
+use common::{run, stderr, stdout, temp_dir};
+
+const STATS: &str = env!("CARGO_BIN_EXE_stats");
+
+#[test]
+fn test_stats_rolls_method() {
+    let dir = temp_dir("stats-roll");
+    let output = run(STATS, &dir, &["--method", "4d6kh3", "--seed", "99"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Seed: 99"));
+
+    let scores: Vec<u8> = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Roll 4d6kh3: "))
+        .expect("roll line missing")
+        .split(", ")
+        .map(|score| score.parse().unwrap())
+        .collect();
+    assert_eq!(scores.len(), 6);
+    assert!(scores.iter().all(|score| (3..=18).contains(score)));
+
+    // The same seed reproduces the same roll
+    let again = run(STATS, &dir, &["--method", "4d6kh3", "--seed", "99"]);
+    assert_eq!(stdout, common::stdout(&again));
+
+    let output = run(STATS, &dir, &["--stats", "2", "--seed", "99"]);
+    let roll = common::stdout(&output);
+    let line = roll.lines().find_map(|line| line.strip_prefix("Roll 3d6: ")).expect("roll line missing");
+    assert_eq!(line.split(", ").count(), 2);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_stats_rejects_invalid_method() {
+    let dir = temp_dir("stats-invalid");
+
+    let output = run(STATS, &dir, &["--method", "20d20"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("scores must be between 0 and 255"));
+
+    let output = run(STATS, &dir, &["--method", "4d6dl5"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("cannot keep or drop 5 of only 4 dice"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_stats_rolls_magic_dice() {
+    let dir = temp_dir("stats-magic-dice");
+    let config = common::config_path();
+
+    for seed in 0..30 {
+        let output = run(STATS, &dir, &[
+            "--config", config.to_str().unwrap(),
+            "--magic-dice", "3",
+            "--archetype", "Necromancer",
+            "--seed", &seed.to_string(),
+        ]);
+        assert!(output.status.success(), "{}", stderr(&output));
+        let stdout = stdout(&output);
+
+        let mut dice: Vec<u8> = stdout
+            .lines()
+            .find_map(|line| line.strip_prefix("Magic Dice (Necromancer): "))
+            .expect("magic dice line missing")
+            .split(", ")
+            .map(|die| die.parse().unwrap())
+            .collect();
+        dice.sort();
+        dice.dedup();
+
+        // Doubles roll a mishap, triples roll doom
+        assert_eq!(stdout.contains("Mishap: "), dice.len() == 2, "{}", stdout);
+        assert_eq!(stdout.contains("Doom: "), dice.len() == 1, "{}", stdout);
+    }
+
+    let output = run(STATS, &dir, &["--config", config.to_str().unwrap(), "--magic-dice", "2", "--archetype", "Nobody"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown archetype: Nobody"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}